@@ -1,8 +1,11 @@
 #![allow(clippy::type_complexity)]
 
-use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy::{
+    ecs::{schedule::ExecutorKind, system::EntityCommands},
+    prelude::*,
+};
 use bevy_eventlistener::prelude::*;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use rand::{seq::IteratorRandom, Rng};
 
 const DENSITY: usize = 20; // percent of nodes with listeners
@@ -49,6 +52,46 @@ fn event_listeners(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("Single Event Type Shared System", |b| {
+        let mut app = shared_system_app();
+        app.update();
+
+        b.iter(|| {
+            black_box(app.update());
+        });
+    });
+
+    // The first frame spawns the listeners, and initializes the callbacks the events reach.
+    group.bench_function("First Frame", |b| {
+        b.iter_batched(
+            || {
+                let mut app = App::new();
+                app.add_plugins(MinimalPlugins)
+                    .add_systems(
+                        Startup,
+                        (
+                            spawn_listener_hierarchy,
+                            add_listeners_to_hierarchy::<DENSITY, 1>,
+                        )
+                            .chain(),
+                    )
+                    .add_plugins(EventListenerPlugin::<TestEvent<1>>::default())
+                    .add_systems(First, send_events::<1, N_EVENTS>);
+                app
+            },
+            |mut app| black_box(app.update()),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("First Frame Shared System", |b| {
+        b.iter_batched(
+            shared_system_app,
+            |mut app| black_box(app.update()),
+            BatchSize::LargeInput,
+        );
+    });
+
     group.bench_function("Single Event Type No Listeners", |b| {
         let mut app = App::new();
         app.add_plugins(MinimalPlugins)
@@ -68,30 +111,26 @@ fn event_listeners(c: &mut Criterion) {
         });
     });
 
+    // The baseline for the multi-threaded benchmark, building each listener graph one at a time.
     group.bench_function("Four Event Types", |b| {
-        let mut app = App::new();
-        const FRAC_N_EVENTS_4: usize = N_EVENTS / 4;
-        const FRAC_DENSITY_4: usize = DENSITY / 4;
+        let mut app = four_event_types_app();
+        app.edit_schedule(PreUpdate, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+        app.update();
 
-        app.add_plugins(MinimalPlugins)
-            .add_systems(
-                Startup,
-                (
-                    spawn_listener_hierarchy,
-                    add_listeners_to_hierarchy::<FRAC_DENSITY_4, 1>,
-                    add_listeners_to_hierarchy::<FRAC_DENSITY_4, 2>,
-                    add_listeners_to_hierarchy::<FRAC_DENSITY_4, 3>,
-                    add_listeners_to_hierarchy::<FRAC_DENSITY_4, 4>,
-                ),
-            )
-            .add_plugins(EventListenerPlugin::<TestEvent<1>>::default())
-            .add_plugins(EventListenerPlugin::<TestEvent<2>>::default())
-            .add_plugins(EventListenerPlugin::<TestEvent<3>>::default())
-            .add_plugins(EventListenerPlugin::<TestEvent<4>>::default())
-            .add_systems(First, send_events::<1, FRAC_N_EVENTS_4>)
-            .add_systems(First, send_events::<2, FRAC_N_EVENTS_4>)
-            .add_systems(First, send_events::<3, FRAC_N_EVENTS_4>)
-            .add_systems(First, send_events::<4, FRAC_N_EVENTS_4>);
+        b.iter(|| {
+            black_box(app.update());
+        });
+    });
+
+    // Listener graphs for each event type are built in parallel by the multi-threaded executor,
+    // which the benchmarks enable with bevy's `multi_threaded` feature.
+    group.bench_function("Four Event Types Multi-threaded", |b| {
+        let mut app = four_event_types_app();
+        app.edit_schedule(PreUpdate, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::MultiThreaded);
+        });
         app.update();
 
         b.iter(|| {
@@ -100,6 +139,50 @@ fn event_listeners(c: &mut Criterion) {
     });
 }
 
+/// Every listener runs the same [`SharedCallback`], instead of its own callback system.
+fn shared_system_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_systems(
+            Startup,
+            (
+                spawn_listener_hierarchy,
+                add_shared_listeners_to_hierarchy::<DENSITY, 1>,
+            )
+                .chain(),
+        )
+        .add_plugins(EventListenerPlugin::<TestEvent<1>>::default())
+        .add_systems(First, send_events::<1, N_EVENTS>);
+    app
+}
+
+fn four_event_types_app() -> App {
+    let mut app = App::new();
+    const FRAC_N_EVENTS_4: usize = N_EVENTS / 4;
+    const FRAC_DENSITY_4: usize = DENSITY / 4;
+
+    app.add_plugins(MinimalPlugins)
+        .add_systems(
+            Startup,
+            (
+                spawn_listener_hierarchy,
+                add_listeners_to_hierarchy::<FRAC_DENSITY_4, 1>,
+                add_listeners_to_hierarchy::<FRAC_DENSITY_4, 2>,
+                add_listeners_to_hierarchy::<FRAC_DENSITY_4, 3>,
+                add_listeners_to_hierarchy::<FRAC_DENSITY_4, 4>,
+            ),
+        )
+        .add_plugins(EventListenerPlugin::<TestEvent<1>>::default())
+        .add_plugins(EventListenerPlugin::<TestEvent<2>>::default())
+        .add_plugins(EventListenerPlugin::<TestEvent<3>>::default())
+        .add_plugins(EventListenerPlugin::<TestEvent<4>>::default())
+        .add_systems(First, send_events::<1, FRAC_N_EVENTS_4>)
+        .add_systems(First, send_events::<2, FRAC_N_EVENTS_4>)
+        .add_systems(First, send_events::<3, FRAC_N_EVENTS_4>)
+        .add_systems(First, send_events::<4, FRAC_N_EVENTS_4>);
+    app
+}
+
 #[derive(Clone, Event, EntityEvent)]
 #[can_bubble]
 struct TestEvent<const N: usize> {
@@ -141,12 +224,30 @@ fn add_listeners_to_hierarchy<const DENSITY: usize, const N: usize>(
         commands.entity(entity).insert(empty_listener::<N>());
     }
     for entity in &nodes {
-        maybe_insert_listener::<DENSITY, N>(&mut commands.entity(entity));
+        maybe_insert_listener::<DENSITY, _>(&mut commands.entity(entity), empty_listener::<N>);
     }
 }
 
-fn maybe_insert_listener<const DENSITY: usize, const N: usize>(commands: &mut EntityCommands) {
+fn add_shared_listeners_to_hierarchy<const DENSITY: usize, const N: usize>(
+    mut commands: Commands,
+    roots_and_leaves: Query<Entity, Or<(Without<Parent>, Without<Children>)>>,
+    nodes: Query<Entity, (With<Parent>, With<Children>)>,
+) {
+    let callback = SharedCallback::new(|| {});
+    let listener = || On::<TestEvent<N>>::run_shared(&callback);
+    for entity in &roots_and_leaves {
+        commands.entity(entity).insert(listener());
+    }
+    for entity in &nodes {
+        maybe_insert_listener::<DENSITY, _>(&mut commands.entity(entity), listener);
+    }
+}
+
+fn maybe_insert_listener<const DENSITY: usize, E: EntityEvent>(
+    commands: &mut EntityCommands,
+    listener: impl Fn() -> On<E>,
+) {
     if rand::thread_rng().gen_bool(DENSITY as f64 / 100.0) {
-        commands.insert(empty_listener::<N>());
+        commands.insert(listener());
     }
 }
@@ -1,62 +1,215 @@
-use core::panic;
-
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
+use syn::{spanned::Spanned, Attribute, Data, DeriveInput, Expr, Fields, Member, Meta};
 
-#[proc_macro_derive(EntityEvent, attributes(target, can_bubble))]
+/// Implements `EntityEvent` for a struct or enum, using the field marked with `#[target]` as the
+/// target entity. Add `#[can_bubble]` to the type to let events bubble up the hierarchy.
+///
+/// The target can be a named or positional field of a struct, and every variant of an enum must
+/// mark its own target field.
+///
+/// The propagation policy can be set with these attributes on the type:
+///
+/// - `#[can_bubble = "field"]` reads a `bool` field of a struct, and `#[can_bubble = "method()"]`
+///   calls a method returning `bool`, to decide whether each event bubbles. These can also be
+///   written as `#[can_bubble(field)]` and `#[can_bubble(method())]`.
+/// - `#[max_depth = 3]` limits how many levels an event can propagate from its target.
+/// - `#[broadcast]` broadcasts events down to descendants in breadth-first order, or
+///   `#[broadcast(depth_first)]` in depth-first order.
+///
+/// ```ignore
+/// #[derive(Clone, Event, EntityEvent)]
+/// #[can_bubble]
+/// struct Clicked(#[target] Entity);
+///
+/// #[derive(Clone, Event, EntityEvent)]
+/// #[can_bubble = "is_bubbling()"]
+/// #[max_depth = 3]
+/// enum Pointer {
+///     Down { #[target] target: Entity, button: u8 },
+///     Up(#[target] Entity),
+/// }
+/// ```
+#[proc_macro_derive(EntityEvent, attributes(target, can_bubble, max_depth, broadcast))]
 pub fn derive(input: TokenStream) -> TokenStream {
-    let ast: syn::DeriveInput = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    derive_entity_event(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn derive_entity_event(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &ast.ident;
     let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-    let mut target = None;
-    let mut can_bubble = false;
-
+    let mut can_bubble = quote! { false };
+    let mut methods = Vec::new();
     for attr in ast.attrs.iter() {
         if attr.path().is_ident("can_bubble") {
-            can_bubble = true;
+            can_bubble = can_bubble_policy(attr, &ast.data)?;
+        } else if attr.path().is_ident("max_depth") {
+            let max_depth: syn::LitInt = match &attr.meta.require_name_value()?.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(lit),
+                    ..
+                }) => lit.clone(),
+                value => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected an integer, e.g. `#[max_depth = 3]`",
+                    ))
+                }
+            };
+            methods.push(quote! {
+                fn max_depth(&self) -> Option<usize> {
+                    Some(#max_depth)
+                }
+            });
+        } else if attr.path().is_ident("broadcast") {
+            let order = match &attr.meta {
+                Meta::Path(_) => quote! { BreadthFirst },
+                Meta::List(list) => match list.parse_args::<syn::Ident>()? {
+                    order if order == "breadth_first" => quote! { BreadthFirst },
+                    order if order == "depth_first" => quote! { DepthFirst },
+                    order => {
+                        return Err(syn::Error::new_spanned(
+                            order,
+                            "expected `breadth_first` or `depth_first`",
+                        ))
+                    }
+                },
+                Meta::NameValue(meta) => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected `#[broadcast]` or `#[broadcast(depth_first)]`",
+                    ))
+                }
+            };
+            methods.push(quote! {
+                fn broadcast(&self) -> Option<::bevy_eventlistener::event_listener::Broadcast> {
+                    Some(::bevy_eventlistener::event_listener::Broadcast::#order)
+                }
+            });
         }
     }
 
-    match ast.data {
-        // Only process structs
-        syn::Data::Struct(ref data_struct) => {
-            // Check the kind of fields the struct contains
-            // Structs with named fields
-            if let syn::Fields::Named(ref fields_named) = data_struct.fields {
-                // Iterate over the fields
-                for field in fields_named.named.iter() {
-                    // Get attributes #[..] on each field
-                    for attr in field.attrs.iter() {
-                        // Parse the attribute
-                        if let syn::Meta::Path(ref path) = attr.meta {
-                            if let Some(ident) = path.get_ident() {
-                                if ident == "target" {
-                                    target = Some(field.ident.clone());
-                                }
-                            }
-                        }
-                    }
+    let target = match &ast.data {
+        Data::Struct(data) => {
+            let target = target_field(&data.fields, name.span())?;
+            quote! { self.#target }
+        }
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in data.variants.iter() {
+                let target = target_field(&variant.fields, variant.ident.span())?;
+                let variant = &variant.ident;
+                arms.push(quote! { Self::#variant { #target: target, .. } => *target, });
+            }
+            if arms.is_empty() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`EntityEvent` can't be derived for an enum without variants",
+                ));
+            }
+            quote! {
+                match self {
+                    #(#arms)*
                 }
             }
         }
-        // Panic when we don't have a struct
-        _ => panic!("Must be a struct"),
-    }
-
-    let Some(target) = target else {
-        panic!("Missing `#[target] attribute. You must annotate the field with the target Entity, or instead manually implement EntityEvent.")
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "`EntityEvent` can only be derived for structs and enums",
+            ))
+        }
     };
 
-    let gen = quote! {
+    Ok(quote! {
         impl #impl_generics EntityEvent for #name #ty_generics #where_clause {
             fn target(&self) -> Entity {
-                self.#target
+                #target
             }
             fn can_bubble(&self) -> bool {
                 #can_bubble
             }
+            #(#methods)*
         }
+    })
+}
+
+/// The body of `can_bubble` for a `#[can_bubble]`, `#[can_bubble(field)]`, or
+/// `#[can_bubble(method())]` attribute.
+fn can_bubble_policy(attr: &Attribute, data: &Data) -> syn::Result<proc_macro2::TokenStream> {
+    let value: Expr = match &attr.meta {
+        Meta::Path(_) => return Ok(quote! { true }),
+        Meta::List(list) => list.parse_args()?,
+        Meta::NameValue(meta) => match &meta.value {
+            Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => lit.parse()?,
+            value => {
+                return Err(syn::Error::new_spanned(
+                    value,
+                    "expected a string, e.g. `#[can_bubble = \"field\"]`",
+                ))
+            }
+        },
     };
-    gen.into()
+    match &value {
+        Expr::Path(path) if path.path.get_ident().is_some() => {
+            if !matches!(data, Data::Struct(_)) {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "a field can only decide whether structs bubble, use a method instead, e.g. \
+                    `#[can_bubble = \"method()\"]`",
+                ));
+            }
+            Ok(quote! { self.#path })
+        }
+        Expr::Call(call) if call.args.is_empty() => match &*call.func {
+            Expr::Path(method) if method.path.get_ident().is_some() => {
+                Ok(quote! { self.#method() })
+            }
+            func => Err(syn::Error::new_spanned(func, "expected a method name")),
+        },
+        value => Err(syn::Error::new_spanned(
+            value,
+            "expected a field, e.g. `#[can_bubble = \"field\"]`, or a method with no arguments, \
+            e.g. `#[can_bubble = \"method()\"]`",
+        )),
+    }
+}
+
+/// Find the field marked with `#[target]`, reporting an error at `span` if there is none, or at the
+/// second attribute if there is more than one.
+fn target_field(fields: &Fields, span: Span) -> syn::Result<Member> {
+    let mut target = None;
+    for (index, field) in fields.iter().enumerate() {
+        for attr in field.attrs.iter() {
+            if !attr.path().is_ident("target") {
+                continue;
+            }
+            attr.meta.require_path_only()?;
+            if target.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[target]` attribute, only one field can be the target",
+                ));
+            }
+            target = Some(match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(index.into()),
+            });
+        }
+    }
+    target.ok_or_else(|| {
+        syn::Error::new(
+            span,
+            "missing `#[target]` attribute. You must annotate the field with the target Entity, or \
+            instead manually implement EntityEvent.",
+        )
+    })
 }
@@ -1,6 +1,15 @@
 //! Implementation of callbacks as one-shot bevy systems.
 
-use bevy_ecs::{prelude::*, system::BoxedSystem};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError,
+};
+
+use bevy_ecs::{
+    prelude::*,
+    system::{BoxedSystem, ReadOnlySystem},
+};
+use bevy_reflect::Reflect;
 
 use crate::EntityEvent;
 
@@ -8,20 +17,27 @@ use crate::EntityEvent;
 /// [`crate::prelude::On`].
 #[derive(Default, Debug)]
 pub enum CallbackSystem {
-    /// The system has been removed, because it is currently being executed in the callback graph
-    /// for event bubbling.
+    /// The system has been removed, because it has been moved into the callback graph for event
+    /// bubbling, or is currently being executed.
     #[default]
     Empty,
     /// A system that has not yet been initialized.
     New(BoxedSystem),
     /// A system that is ready to be executed.
     Initialized(BoxedSystem),
+    /// A system shared by many listeners.
+    Shared(SharedCallback),
 }
 
 impl CallbackSystem {
     pub(crate) fn run(&mut self, world: &mut World) {
         let mut system = match std::mem::take(self) {
             CallbackSystem::Empty => return,
+            CallbackSystem::Shared(shared) => {
+                shared.run(world);
+                *self = CallbackSystem::Shared(shared);
+                return;
+            }
             CallbackSystem::New(mut system) => {
                 system.initialize(world);
                 system
@@ -32,9 +48,216 @@ impl CallbackSystem {
         system.apply_deferred(world);
         *self = CallbackSystem::Initialized(system);
     }
+}
+
+/// A handle to a single callback system, with its own state, that can be shared by many
+/// [`On`](crate::prelude::On) listeners with [`On::run_shared`](crate::prelude::On::run_shared).
+/// Clones of the handle refer to the same system.
+#[derive(Clone, Debug)]
+pub struct SharedCallback(Arc<Mutex<CallbackSystem>>);
+
+impl SharedCallback {
+    /// Create a callback system that can be shared by many listeners. Like the callbacks of
+    /// [`On::run`](crate::prelude::On::run), the system can access the [`ListenerInput`].
+    pub fn new<Marker>(callback: impl IntoSystem<(), (), Marker>) -> Self {
+        Self(Arc::new(Mutex::new(CallbackSystem::New(Box::new(
+            IntoSystem::into_system(callback),
+        )))))
+    }
+
+    fn run(&self, world: &mut World) {
+        self.lock().run(world);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CallbackSystem> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The systems of the [`SharedCallback`]s run while dispatching events, which are taken out of their
+/// handles the first time they are run, so later runs don't need to lock the handle. They must be
+/// moved back with [`SharedSystems::restore`] once dispatch is done.
+#[derive(Default)]
+pub(crate) struct SharedSystems(Vec<(SharedCallback, CallbackSystem)>);
+
+impl SharedSystems {
+    /// Run a callback system, using the taken system if it is a [`SharedCallback`].
+    pub(crate) fn run(&mut self, callback: &mut CallbackSystem, world: &mut World) {
+        let CallbackSystem::Shared(shared) = callback else {
+            callback.run(world);
+            return;
+        };
+        let index = match self
+            .0
+            .iter()
+            .position(|(taken, _)| Arc::ptr_eq(&taken.0, &shared.0))
+        {
+            Some(index) => index,
+            None => {
+                let system = std::mem::take(&mut *shared.lock());
+                self.0.push((shared.clone(), system));
+                self.0.len() - 1
+            }
+        };
+        self.0[index].1.run(world);
+    }
+
+    /// Move every taken system back into its [`SharedCallback`].
+    pub(crate) fn restore(&mut self) {
+        for (shared, system) in self.0.drain(..) {
+            *shared.lock() = system;
+        }
+    }
+}
+
+/// A callback system that only has read access to the world, and receives its [`ListenerInput`] as
+/// system input. These can be run concurrently with other read-only callbacks.
+pub(crate) struct ReadOnlyCallback<E: EntityEvent> {
+    system: Box<dyn ReadOnlySystem<In = ListenerInput<E>, Out = ()>>,
+    initialized: bool,
+}
+
+impl<E: EntityEvent> ReadOnlyCallback<E> {
+    /// Initialize the system if this is the first time it will be run.
+    pub(crate) fn initialize(&mut self, world: &mut World) {
+        if !self.initialized {
+            self.system.initialize(world);
+            self.initialized = true;
+        }
+    }
+
+    /// Run the system. This must be initialized first, and [`Self::apply_deferred`] must be called
+    /// once the world can be mutated again.
+    pub(crate) fn run(&mut self, input: ListenerInput<E>, world: &World) {
+        self.system.run_readonly(input, world);
+    }
+
+    pub(crate) fn apply_deferred(&mut self, world: &mut World) {
+        self.system.apply_deferred(world);
+    }
+}
+
+/// The callback of an [`On`](crate::prelude::On) listener, and how the dispatcher runs it.
+pub(crate) enum ListenerCallback<E: EntityEvent> {
+    /// Run once for each event, with exclusive access to the world.
+    Exclusive(CallbackSystem),
+    /// Run once per frame with a [`ListenerBatch`] of every event that reached the listener.
+    Batch(CallbackSystem),
+    /// Run once for each event, with read-only access to the world, concurrently with other
+    /// read-only callbacks.
+    ReadOnly(ReadOnlyCallback<E>),
+}
+
+impl<E: EntityEvent> Default for ListenerCallback<E> {
+    fn default() -> Self {
+        Self::Exclusive(CallbackSystem::Empty)
+    }
+}
+
+impl<E: EntityEvent> ListenerCallback<E> {
+    pub(crate) fn read_only(system: impl ReadOnlySystem<In = ListenerInput<E>, Out = ()>) -> Self {
+        Self::ReadOnly(ReadOnlyCallback {
+            system: Box::new(system),
+            initialized: false,
+        })
+    }
 
     pub(crate) fn is_empty(&self) -> bool {
-        matches!(self, CallbackSystem::Empty)
+        matches!(
+            self,
+            Self::Exclusive(CallbackSystem::Empty) | Self::Batch(CallbackSystem::Empty)
+        )
+    }
+}
+
+/// A shared handle to the [`ListenerCallback`] of an [`On`](crate::prelude::On) listener.
+///
+/// The [`EventDispatcher`](crate::event_dispatcher::EventDispatcher) moves the callback out of the
+/// handle and into its listener graph the first time an event reaches the listener, and moves it
+/// back if that part of the graph is discarded. This goes through the handle instead of the
+/// component, so dispatching events never mutates the listener or triggers change detection.
+pub(crate) struct Callback<E: EntityEvent>(Arc<CallbackSlot<E>>);
+
+struct CallbackSlot<E: EntityEvent> {
+    callback: Mutex<ListenerCallback<E>>,
+    /// Is the listener's callback empty? This doesn't change while the callback is moved out of the
+    /// handle, so it describes the callback wherever it is.
+    empty: AtomicBool,
+    /// Decides which events the callback is run for, if it isn't run for every event.
+    filter: Option<EventFilter<E>>,
+}
+
+/// Decides whether a listener's callback is run for an event, set with [`Callback::with_filter`].
+type EventFilter<E> = Box<dyn Fn(&E) -> bool + Send + Sync>;
+
+impl<E: EntityEvent> Clone for Callback<E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<E: EntityEvent> Default for Callback<E> {
+    fn default() -> Self {
+        Self::new(ListenerCallback::default())
+    }
+}
+
+impl<E: EntityEvent> Callback<E> {
+    pub(crate) fn new(callback: ListenerCallback<E>) -> Self {
+        Self(Arc::new(CallbackSlot {
+            empty: AtomicBool::new(callback.is_empty()),
+            callback: Mutex::new(callback),
+            filter: None,
+        }))
+    }
+
+    /// Move the callback into a new handle that only runs it for events `filter` accepts. Other
+    /// events pass through the listener as if it had no callback.
+    pub(crate) fn with_filter(self, filter: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(CallbackSlot {
+            empty: AtomicBool::new(self.is_empty()),
+            callback: Mutex::new(self.take()),
+            filter: Some(Box::new(filter)),
+        }))
+    }
+
+    /// Take the callback out of the handle, leaving an empty one behind.
+    pub(crate) fn take(&self) -> ListenerCallback<E> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Move a callback taken with [`Callback::take`] back into the handle.
+    pub(crate) fn restore(&self, callback: ListenerCallback<E>) {
+        self.0.empty.store(callback.is_empty(), Ordering::Relaxed);
+        *self.lock() = callback;
+    }
+
+    /// Does the listener have no callback? Unlike checking the callback in the handle, this is
+    /// `false` while the callback has been moved out by [`Callback::take`].
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.empty.load(Ordering::Relaxed)
+    }
+
+    /// Is the callback run for `event`? Callbacks without a filter are run for every event.
+    pub(crate) fn accepts(&self, event: &E) -> bool {
+        self.0.filter.as_ref().is_none_or(|filter| filter(event))
+    }
+
+    /// Does the callback have a filter, so it may not be run for every event?
+    pub(crate) fn is_filtered(&self) -> bool {
+        self.0.filter.is_some()
+    }
+
+    /// Is this a handle to the same callback as `other`?
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ListenerCallback<E>> {
+        self.0
+            .callback
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 }
 
@@ -50,6 +273,24 @@ pub type Listener<'w, E> = Res<'w, ListenerInput<E>>;
 /// Use this in callback systems to access event data for the event that triggered the callback.
 pub type ListenerMut<'w, E> = ResMut<'w, ListenerInput<E>>;
 
+/// A [`SystemParam`](bevy_ecs::system::SystemParam) used to get immutable access to the
+/// [`ListenerBatch`] for this batch callback.
+///
+/// Use this in callback systems added with [`On::run_batch`](crate::prelude::On::run_batch) to
+/// access every event that reached the listener this frame.
+pub type BatchListener<'w, E> = Res<'w, ListenerBatch<E>>;
+
+/// A [`SystemParam`](bevy_ecs::system::SystemParam) used to get mutable access to the
+/// [`ListenerBatch`] for this batch callback.
+///
+/// Use this in callback systems added with [`On::run_batch`](crate::prelude::On::run_batch) to
+/// access every event that reached the listener this frame.
+pub type BatchListenerMut<'w, E> = ResMut<'w, ListenerBatch<E>>;
+
+/// A [`SystemParam`](bevy_ecs::system::SystemParam) used to get the [`AnyListenerInput`] for this
+/// [`OnAny`](crate::event_listener::OnAny) callback.
+pub type AnyListener<'w> = Res<'w, AnyListenerInput>;
+
 /// Data from an event that triggered an [`On<Event>`](crate::event_listener::On) listener, and is
 /// currently bubbling through the entity hierarchy.
 ///
@@ -73,6 +314,8 @@ pub type ListenerMut<'w, E> = ResMut<'w, ListenerInput<E>>;
 ///     event.foo += 1; // Mutate the event that is being bubbled
 ///     event.target(); // The entity that was originally targeted
 ///     event.listener(); // The entity that was listening for this event
+///     event.depth(); // How many levels up the hierarchy the listener is from the target
+///     event.previous_listener(); // The listener the event passed through before this one
 ///     event.stop_propagation(); // Stop the event from bubbling further
 /// }
 /// ```
@@ -80,9 +323,25 @@ pub type ListenerMut<'w, E> = ResMut<'w, ListenerInput<E>>;
 pub struct ListenerInput<E: EntityEvent> {
     /// The entity that was listening for this event.
     pub(crate) listener: Entity,
+    /// The entity targeted by this bubble.
+    pub(crate) target: Entity,
     /// Event-specific information.
     pub(crate) event_data: E,
     pub(crate) propagate: bool,
+    /// Every listener the event can bubble through, starting from the first listener at or above
+    /// the target, with the number of ancestors of each listener, and whether it has a callback for
+    /// this event. Entities that only have an [`OnAny`](crate::prelude::OnAny) are in the path so
+    /// the event visits them, but are skipped by the path accessors.
+    pub(crate) path: Arc<[(Entity, usize, bool)]>,
+    /// The index of this listener in `path`.
+    pub(crate) index: usize,
+    /// The number of ancestors of the target.
+    pub(crate) target_level: usize,
+    /// How many levels the listener is from the target, up for bubbling events and down for
+    /// broadcast events.
+    pub(crate) depth: usize,
+    /// Set by [`ListenerInput::skip_descendants`] while broadcasting.
+    pub(crate) skip_descendants: bool,
 }
 
 impl<E: EntityEvent> ListenerInput<E> {
@@ -93,10 +352,69 @@ impl<E: EntityEvent> ListenerInput<E> {
         self.listener
     }
 
-    /// When called, the event will stop bubbling up the hierarchy to its parent.
+    /// The entity that was targeted by the event, where it started bubbling from. For events with
+    /// several [`targets`](EntityEvent::targets), this is the target whose bubble reached this
+    /// listener.
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    /// When called, the event will stop bubbling up the hierarchy to its parent. For
+    /// [broadcast](EntityEvent::broadcast) events, this stops the event from reaching any more
+    /// descendants of the target.
     pub fn stop_propagation(&mut self) {
         self.propagate = false;
     }
+
+    /// When called during a [broadcast](EntityEvent::broadcast), the event will not be delivered to
+    /// the descendants of this listener, but continues to the rest of the target's descendants.
+    /// This has no effect on events that bubble.
+    pub fn skip_descendants(&mut self) {
+        self.skip_descendants = true;
+    }
+
+    /// How many levels up the hierarchy the listener is from the target. This is `0` when the
+    /// listener is the target, and `1` when the event came from a direct child of the listener.
+    /// For [broadcast](EntityEvent::broadcast) events, this is how many levels down the hierarchy
+    /// the listener is from the target instead.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The index of this listener in the event's path, which is the number of listeners the event
+    /// passed through before reaching this one.
+    pub fn path_index(&self) -> usize {
+        match self.path.get(..self.index) {
+            Some(passed) => passed.iter().filter(|&&(_, _, callback)| callback).count(),
+            // Broadcast events have no path, and count the listeners they visit instead.
+            None => self.index,
+        }
+    }
+
+    /// The listener the event passed through before reaching this one, if any. This is always
+    /// `None` for [broadcast](EntityEvent::broadcast) events.
+    pub fn previous_listener(&self) -> Option<Entity> {
+        let passed = self.path.get(..self.index)?;
+        passed
+            .iter()
+            .rev()
+            .find(|&&(_, _, callback)| callback)
+            .map(|&(listener, _, _)| listener)
+    }
+
+    /// The listeners the event will pass through after this one, in order, unless propagation is
+    /// stopped. This is empty for events that cannot bubble, and for
+    /// [broadcast](EntityEvent::broadcast) events.
+    pub fn remaining_path(&self) -> impl Iterator<Item = Entity> + '_ {
+        let remaining = match self.can_bubble() {
+            true => self.path.get(self.index + 1..).unwrap_or_default(),
+            false => &[],
+        };
+        remaining
+            .iter()
+            .filter(|&&(_, _, callback)| callback)
+            .map(|&(listener, _, _)| listener)
+    }
 }
 
 impl<E: EntityEvent> std::ops::Deref for ListenerInput<E> {
@@ -112,3 +430,137 @@ impl<E: EntityEvent> std::ops::DerefMut for ListenerInput<E> {
         &mut self.event_data
     }
 }
+
+/// A type-erased view of an event that reached an [`OnAny`](crate::event_listener::OnAny)
+/// listener, which can be any [`EntityEvent`] type with an
+/// [`EventListenerPlugin`](crate::EventListenerPlugin).
+///
+/// This is accessed as a bevy resource in the callback system, more easily accessed with the
+/// [`AnyListener`] system param.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::prelude::*;
+/// fn log_events(event: AnyListener) {
+///     info!(
+///         "{} reached {:?} from {:?}: {:?}",
+///         event.event_type(),
+///         event.listener(),
+///         event.target(),
+///         event.event()
+///     );
+/// }
+/// # let _ = OnAny::run(log_events);
+/// ```
+#[derive(Resource)]
+pub struct AnyListenerInput {
+    pub(crate) event_type: &'static str,
+    pub(crate) listener: Entity,
+    pub(crate) target: Entity,
+    pub(crate) event: Option<Box<dyn Reflect>>,
+}
+
+impl AnyListenerInput {
+    /// The type name of the event.
+    pub fn event_type(&self) -> &'static str {
+        self.event_type
+    }
+
+    /// The entity that was listening for the event.
+    pub fn listener(&self) -> Entity {
+        self.listener
+    }
+
+    /// The entity that was targeted by the event, where it started propagating from.
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    /// The event, if its type implements [`Reflect`] and is registered in the
+    /// [`AppTypeRegistry`], e.g. with `App::register_type`.
+    /// This is a copy of the event as it was when it reached the listener.
+    pub fn event(&self) -> Option<&dyn Reflect> {
+        self.event.as_deref()
+    }
+}
+
+/// Every event that reached a batch listener, added with
+/// [`On::run_batch`](crate::prelude::On::run_batch), this frame.
+///
+/// This is accessed as a bevy resource in the batch callback system, in place of
+/// [`ListenerInput`]. Events are in the order they reached the listener, and each one can stop its
+/// own propagation.
+///
+/// ```
+/// # use bevy_eventlistener::prelude::*;
+/// # use bevy_ecs::prelude::*;
+/// # #[derive(Clone, Event)]
+/// # struct Damage {
+/// #     target: Entity,
+/// #     amount: u32,
+/// # }
+/// # impl EntityEvent for Damage {
+/// #     fn target(&self) -> Entity {
+/// #         self.target
+/// #     }
+/// # }
+/// fn absorb_damage(mut batch: BatchListenerMut<Damage>) {
+///     let mut absorbed = 0;
+///     for event in &mut batch {
+///         if absorbed < 100 {
+///             absorbed += event.amount;
+///             event.stop_propagation(); // Only this event stops bubbling
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, PartialEq, Debug, Resource)]
+pub struct ListenerBatch<E: EntityEvent> {
+    pub(crate) listener: Entity,
+    pub(crate) inputs: Vec<ListenerInput<E>>,
+}
+
+impl<E: EntityEvent> ListenerBatch<E> {
+    /// The entity that was listening for these events.
+    pub fn listener(&self) -> Entity {
+        self.listener
+    }
+
+    /// The number of events in the batch.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Is the batch empty? Batch callbacks are only run when at least one event reached them.
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Iterate over the events in the order they reached the listener.
+    pub fn iter(&self) -> std::slice::Iter<'_, ListenerInput<E>> {
+        self.inputs.iter()
+    }
+
+    /// Mutably iterate over the events in the order they reached the listener.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, ListenerInput<E>> {
+        self.inputs.iter_mut()
+    }
+}
+
+impl<'a, E: EntityEvent> IntoIterator for &'a ListenerBatch<E> {
+    type Item = &'a ListenerInput<E>;
+    type IntoIter = std::slice::Iter<'a, ListenerInput<E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, E: EntityEvent> IntoIterator for &'a mut ListenerBatch<E> {
+    type Item = &'a mut ListenerInput<E>;
+    type IntoIter = std::slice::IterMut<'a, ListenerInput<E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,224 @@
+//! Provides delayed entity events, which are held until a deadline and then dispatched like any
+//! other event.
+//!
+//! Events are scheduled with [`DelayedEventCommands::send_delayed`], or directly on the
+//! [`DelayedEvents`] resource, with a [`Delay`] measured in [`Time`] or in frames. Each scheduled
+//! event returns a [`DelayedEventHandle`] that can cancel it. Events whose targets are all despawned
+//! before their deadline are dropped.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_eventlistener::prelude::*;
+//! # use std::time::Duration;
+//! # #[derive(Clone, Event, EntityEvent)]
+//! # struct Damage(#[target] Entity);
+//! # #[derive(Event)]
+//! # struct Poison(Entity);
+//! # #[derive(Event)]
+//! # struct Antidote(Entity);
+//! #[derive(Component)]
+//! struct Poisoned(DelayedEventHandle);
+//!
+//! // Poison deals its damage after three seconds...
+//! fn poison(mut commands: Commands, mut poisons: EventReader<Poison>) {
+//!     for Poison(entity) in poisons.read() {
+//!         let delay = Delay::Time(Duration::from_secs(3));
+//!         let damage = commands.send_delayed(Damage(*entity), delay);
+//!         commands.entity(*entity).insert(Poisoned(damage));
+//!     }
+//! }
+//!
+//! // ...unless an antidote is taken first.
+//! fn cure(
+//!     mut commands: Commands,
+//!     mut antidotes: EventReader<Antidote>,
+//!     poisoned: Query<&Poisoned>,
+//! ) {
+//!     for Antidote(entity) in antidotes.read() {
+//!         if let Ok(Poisoned(damage)) = poisoned.get(*entity) {
+//!             damage.cancel();
+//!             commands.entity(*entity).remove::<Poisoned>();
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy_ecs::{entity::Entities, prelude::*, world::Command};
+use bevy_time::Time;
+
+use crate::EntityEvent;
+
+/// How long a delayed event is held before it is dispatched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Delay {
+    /// Dispatch the event once this much [`Time`] has elapsed. The event is dispatched on the first
+    /// frame after the deadline, so it may be late by up to a frame.
+    Time(Duration),
+    /// Dispatch the event after this many frames. Delays of `0` and `1` both dispatch the event the
+    /// next time events are dispatched, like an event sent with an [`EventWriter`].
+    Frames(u32),
+}
+
+/// A handle to a delayed event, used to cancel it before it is dispatched. Clones of the handle
+/// refer to the same event.
+#[derive(Clone, Debug, Default)]
+pub struct DelayedEventHandle(Arc<AtomicBool>);
+
+impl DelayedEventHandle {
+    /// Cancel the event, so it is dropped instead of being dispatched. This does nothing if the
+    /// event has already been dispatched.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Has the event been cancelled?
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The deadline of a delayed event, in the units of its [`Delay`].
+#[derive(Clone, Copy, Debug)]
+enum Deadline {
+    Time(Duration),
+    Frame(u64),
+}
+
+/// An event held by [`DelayedEvents`] until its deadline.
+struct DelayedEvent<E: EntityEvent> {
+    event: E,
+    deadline: Deadline,
+    handle: DelayedEventHandle,
+}
+
+/// The delayed events of type `E` waiting to be dispatched. These are released into the
+/// [`Events<E>`] queue by [`DelayedEvents::release`], before the
+/// [`EventDispatcher`](crate::event_dispatcher::EventDispatcher) builds the listener graph.
+#[derive(Resource)]
+pub struct DelayedEvents<E: EntityEvent> {
+    events: Vec<DelayedEvent<E>>,
+    /// The number of times events have been released, used for frame deadlines.
+    frame: u64,
+    /// The elapsed [`Time`] the last time events were released.
+    elapsed: Duration,
+}
+
+impl<E: EntityEvent> Default for DelayedEvents<E> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            frame: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl<E: EntityEvent> DelayedEvents<E> {
+    /// Hold `event` until `delay` has passed, then dispatch it. Returns a handle that can cancel the
+    /// event.
+    pub fn send(&mut self, event: E, delay: Delay) -> DelayedEventHandle {
+        let handle = DelayedEventHandle::default();
+        self.push(event, delay, handle.clone());
+        handle
+    }
+
+    fn push(&mut self, event: E, delay: Delay, handle: DelayedEventHandle) {
+        let deadline = match delay {
+            Delay::Time(duration) => Deadline::Time(self.elapsed + duration),
+            Delay::Frames(frames) => Deadline::Frame(self.frame + frames as u64),
+        };
+        self.events.push(DelayedEvent {
+            event,
+            deadline,
+            handle,
+        });
+    }
+
+    /// The number of delayed events waiting to be dispatched, including cancelled events that have
+    /// not been dropped yet.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Are there no delayed events waiting to be dispatched?
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Sends the delayed events that have reached their deadline, and drops events that were
+    /// cancelled, or whose targets no longer exist. Events with some targets that still exist are
+    /// sent, and the [`EventDispatcher`](crate::event_dispatcher::EventDispatcher) sends a
+    /// [`DeadTargetEvent`](crate::event_dispatcher::DeadTargetEvent) for each target that doesn't.
+    pub fn release(
+        mut delayed: ResMut<DelayedEvents<E>>,
+        mut events: EventWriter<E>,
+        time: Option<Res<Time>>,
+        entities: &Entities,
+    ) {
+        let delayed = delayed.as_mut();
+        delayed.frame += 1;
+        if let Some(time) = time {
+            delayed.elapsed = time.elapsed();
+        }
+        if delayed.events.is_empty() {
+            return;
+        }
+        let (frame, elapsed) = (delayed.frame, delayed.elapsed);
+        delayed.events.retain_mut(|delayed| {
+            let mut targets = delayed.event.targets();
+            if delayed.handle.is_cancelled() || !targets.any(|target| entities.contains(target)) {
+                return false;
+            }
+            let ready = match delayed.deadline {
+                Deadline::Time(deadline) => elapsed >= deadline,
+                Deadline::Frame(deadline) => frame >= deadline,
+            };
+            if ready {
+                events.send(delayed.event.clone());
+            }
+            !ready
+        });
+    }
+}
+
+/// Adds methods to [`Commands`] for sending delayed entity events.
+pub trait DelayedEventCommands {
+    /// Hold `event` until `delay` has passed, then dispatch it. Returns a handle that can cancel the
+    /// event. The event is dropped if all of its targets are despawned first.
+    fn send_delayed<E: EntityEvent>(&mut self, event: E, delay: Delay) -> DelayedEventHandle;
+}
+
+impl DelayedEventCommands for Commands<'_, '_> {
+    fn send_delayed<E: EntityEvent>(&mut self, event: E, delay: Delay) -> DelayedEventHandle {
+        let handle = DelayedEventHandle::default();
+        self.add(SendDelayed {
+            event,
+            delay,
+            handle: handle.clone(),
+        });
+        handle
+    }
+}
+
+/// The [`Command`] used by [`DelayedEventCommands::send_delayed`].
+struct SendDelayed<E: EntityEvent> {
+    event: E,
+    delay: Delay,
+    handle: DelayedEventHandle,
+}
+
+impl<E: EntityEvent> Command for SendDelayed<E> {
+    fn apply(self, world: &mut World) {
+        world
+            .resource_mut::<DelayedEvents<E>>()
+            .push(self.event, self.delay, self.handle);
+    }
+}
@@ -0,0 +1,118 @@
+//! Provides the [`EventListenerDiagnosticsPlugin`], which records [`DispatchStats`] as bevy
+//! [`Diagnostic`]s.
+
+use std::marker::PhantomData;
+
+use bevy_app::prelude::*;
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy_ecs::prelude::*;
+
+use crate::{
+    event_dispatcher::{DispatchStats, EventDispatcher},
+    EntityEvent, EventListenerSet,
+};
+
+/// Records the [`DispatchStats`] of the [`EventDispatcher<E>`] every frame as bevy [`Diagnostic`]s.
+///
+/// Diagnostics are registered under `event_listener/<event type name>/`, see
+/// [`DispatchDiagnosticPaths`] for the full list. Counters are recorded as the number of events or
+/// listeners handled that frame, and timings are in milliseconds.
+///
+/// This should be added alongside the [`EventListenerPlugin<E>`](crate::EventListenerPlugin) for
+/// the same event type.
+pub struct EventListenerDiagnosticsPlugin<E: EntityEvent>(PhantomData<E>);
+
+impl<E: EntityEvent> Default for EventListenerDiagnosticsPlugin<E> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: EntityEvent> Plugin for EventListenerDiagnosticsPlugin<E> {
+    fn build(&self, app: &mut App) {
+        let paths = DispatchDiagnosticPaths::<E>::default();
+        for (path, suffix) in [
+            (&paths.events_received, ""),
+            (&paths.events_dead_branch, ""),
+            (&paths.listeners_invoked, ""),
+            (&paths.graph_nodes_built, ""),
+            (&paths.build_time, "ms"),
+            (&paths.bubble_time, "ms"),
+            (&paths.cleanup_time, "ms"),
+        ] {
+            app.register_diagnostic(Diagnostic::new(path.clone()).with_suffix(suffix));
+        }
+        app.insert_resource(paths)
+            .add_systems(PreUpdate, record_diagnostics::<E>.after(EventListenerSet));
+    }
+}
+
+/// The [`DiagnosticPath`]s used by the [`EventListenerDiagnosticsPlugin<E>`].
+#[derive(Resource)]
+pub struct DispatchDiagnosticPaths<E: EntityEvent> {
+    /// Path for [`DispatchStats::events_received`].
+    pub events_received: DiagnosticPath,
+    /// Path for [`DispatchStats::events_dead_branch`].
+    pub events_dead_branch: DiagnosticPath,
+    /// Path for [`DispatchStats::listeners_invoked`].
+    pub listeners_invoked: DiagnosticPath,
+    /// Path for [`DispatchStats::graph_nodes_built`].
+    pub graph_nodes_built: DiagnosticPath,
+    /// Path for [`DispatchStats::build_time`].
+    pub build_time: DiagnosticPath,
+    /// Path for [`DispatchStats::bubble_time`].
+    pub bubble_time: DiagnosticPath,
+    /// Path for [`DispatchStats::cleanup_time`].
+    pub cleanup_time: DiagnosticPath,
+    phantom: PhantomData<E>,
+}
+
+impl<E: EntityEvent> Default for DispatchDiagnosticPaths<E> {
+    fn default() -> Self {
+        let path = |name: &str| {
+            DiagnosticPath::from_components(["event_listener", std::any::type_name::<E>(), name])
+        };
+        Self {
+            events_received: path("events_received"),
+            events_dead_branch: path("events_dead_branch"),
+            listeners_invoked: path("listeners_invoked"),
+            graph_nodes_built: path("graph_nodes_built"),
+            build_time: path("build_time"),
+            bubble_time: path("bubble_time"),
+            cleanup_time: path("cleanup_time"),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Takes the stats collected by the dispatcher this frame and records them. Stats are enabled the
+/// first time this runs, so nothing is recorded until the following frame.
+fn record_diagnostics<E: EntityEvent>(
+    dispatcher: Option<ResMut<EventDispatcher<E>>>,
+    paths: Res<DispatchDiagnosticPaths<E>>,
+    mut diagnostics: Diagnostics,
+) {
+    let Some(mut dispatcher) = dispatcher else {
+        return;
+    };
+    let Some(stats) = dispatcher.take_stats() else {
+        dispatcher.enable_stats();
+        return;
+    };
+    let DispatchStats {
+        events_received,
+        events_dead_branch,
+        listeners_invoked,
+        graph_nodes_built,
+        build_time,
+        bubble_time,
+        cleanup_time,
+    } = stats;
+    diagnostics.add_measurement(&paths.events_received, || events_received as f64);
+    diagnostics.add_measurement(&paths.events_dead_branch, || events_dead_branch as f64);
+    diagnostics.add_measurement(&paths.listeners_invoked, || listeners_invoked as f64);
+    diagnostics.add_measurement(&paths.graph_nodes_built, || graph_nodes_built as f64);
+    diagnostics.add_measurement(&paths.build_time, || build_time.as_secs_f64() * 1000.0);
+    diagnostics.add_measurement(&paths.bubble_time, || bubble_time.as_secs_f64() * 1000.0);
+    diagnostics.add_measurement(&paths.cleanup_time, || cleanup_time.as_secs_f64() * 1000.0);
+}
@@ -0,0 +1,226 @@
+//! Provides [`DynamicEvent`], for entity events with types that are defined at runtime, e.g. by
+//! data files or mods, instead of as Rust types.
+//!
+//! Dynamic event types are registered by name in the [`DynamicEventRegistry`], which assigns each
+//! one a [`DynamicEventId`]. Every dynamic event is sent as a [`DynamicEvent`], so they all share
+//! the same [`EventDispatcher`](crate::event_dispatcher::EventDispatcher) and bubbling machinery,
+//! and [`DynamicListeners`] route each event to the callback for its id.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_eventlistener::{dynamic::*, prelude::*};
+//! let mut app = App::new();
+//! app.add_plugins(DynamicEventPlugin);
+//! let damage = app
+//!     .world_mut()
+//!     .resource_mut::<DynamicEventRegistry>()
+//!     .register("damage", true);
+//!
+//! let goblin = app
+//!     .world_mut()
+//!     .spawn(
+//!         DynamicListeners::default()
+//!             .on(damage, |event: Listener<DynamicEvent>| {
+//!                 info!("{:?} took {:?} damage", event.listener(), event.payload());
+//!             })
+//!             .build(),
+//!     )
+//!     .id();
+//!
+//! let event = app
+//!     .world()
+//!     .resource::<DynamicEventRegistry>()
+//!     .event(damage, goblin, 10u32);
+//! app.world_mut().send_event(event);
+//! app.update();
+//! ```
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_reflect::{FromReflect, FromType, Reflect, ReflectFromReflect};
+use bevy_utils::{HashMap, HashSet};
+
+use crate::{
+    callbacks::{CallbackSystem, ListenerInput},
+    event_listener::On,
+    EntityEvent, EventListenerPlugin,
+};
+
+/// Adds the [`DynamicEventRegistry`], and event listening and bubbling support for
+/// [`DynamicEvent`]s.
+pub struct DynamicEventPlugin;
+
+impl Plugin for DynamicEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DynamicEventRegistry>()
+            .add_plugins(EventListenerPlugin::<DynamicEvent>::default());
+    }
+}
+
+/// Identifies a dynamic event type registered in the [`DynamicEventRegistry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DynamicEventId(u32);
+
+/// A dynamic event type registered in the [`DynamicEventRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicEventInfo {
+    /// The unique name of the event type.
+    pub name: String,
+    /// Should events of this type bubble up the entity hierarchy, starting from the target?
+    pub can_bubble: bool,
+}
+
+/// The dynamic event types that have been registered, and their [`DynamicEventId`]s.
+#[derive(Resource, Default)]
+pub struct DynamicEventRegistry {
+    events: Vec<DynamicEventInfo>,
+    ids: HashMap<String, DynamicEventId>,
+}
+
+impl DynamicEventRegistry {
+    /// Register a dynamic event type, returning its id. If an event type with the same name has
+    /// already been registered, it is updated to use `can_bubble` and keeps its id.
+    pub fn register(&mut self, name: impl Into<String>, can_bubble: bool) -> DynamicEventId {
+        let name = name.into();
+        if let Some(&id) = self.ids.get(&name) {
+            self.events[id.0 as usize].can_bubble = can_bubble;
+            return id;
+        }
+        let id = DynamicEventId(self.events.len() as u32);
+        self.ids.insert(name.clone(), id);
+        self.events.push(DynamicEventInfo { name, can_bubble });
+        id
+    }
+
+    /// The id of the event type registered with this name, if any.
+    pub fn id(&self, name: &str) -> Option<DynamicEventId> {
+        self.ids.get(name).copied()
+    }
+
+    /// The registered event type with this id, if any.
+    pub fn info(&self, id: DynamicEventId) -> Option<&DynamicEventInfo> {
+        self.events.get(id.0 as usize)
+    }
+
+    /// Create an event of the registered type `id` that targets `target`, carrying `payload`.
+    /// Returns an event that does not bubble if `id` was not registered here.
+    pub fn event<P: FromReflect>(
+        &self,
+        id: DynamicEventId,
+        target: Entity,
+        payload: P,
+    ) -> DynamicEvent {
+        DynamicEvent {
+            id,
+            target,
+            can_bubble: self.info(id).is_some_and(|info| info.can_bubble),
+            payload: Box::new(payload),
+            from_reflect: <ReflectFromReflect as FromType<P>>::from_type(),
+        }
+    }
+}
+
+/// An entity event with a type defined at runtime, identified by its [`DynamicEventId`], and
+/// carrying a reflected payload. These are created with [`DynamicEventRegistry::event`].
+///
+/// Cloning the event rebuilds the payload as its original type with [`ReflectFromReflect`], so
+/// listeners can downcast it to that type even though the event is cloned as it is dispatched.
+#[derive(Event)]
+pub struct DynamicEvent {
+    id: DynamicEventId,
+    target: Entity,
+    can_bubble: bool,
+    payload: Box<dyn Reflect>,
+    /// Rebuilds the payload as its original type when the event is cloned.
+    from_reflect: ReflectFromReflect,
+}
+
+impl DynamicEvent {
+    /// The id of the event's type.
+    pub fn id(&self) -> DynamicEventId {
+        self.id
+    }
+
+    /// The data carried by the event.
+    pub fn payload(&self) -> &dyn Reflect {
+        &*self.payload
+    }
+
+    /// Mutable access to the data carried by the event, which can be changed by listeners as the
+    /// event bubbles.
+    pub fn payload_mut(&mut self) -> &mut dyn Reflect {
+        &mut *self.payload
+    }
+}
+
+impl Clone for DynamicEvent {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            target: self.target,
+            can_bubble: self.can_bubble,
+            payload: self
+                .from_reflect
+                .from_reflect(&*self.payload)
+                .unwrap_or_else(|| self.payload.clone_value()),
+            from_reflect: self.from_reflect.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for DynamicEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicEvent")
+            .field("id", &self.id)
+            .field("target", &self.target)
+            .field("can_bubble", &self.can_bubble)
+            .field("payload", &self.payload)
+            .finish()
+    }
+}
+
+impl EntityEvent for DynamicEvent {
+    fn target(&self) -> Entity {
+        self.target
+    }
+
+    fn can_bubble(&self) -> bool {
+        self.can_bubble
+    }
+}
+
+/// Builds an [`On<DynamicEvent>`] listener with a callback for each dynamic event type it listens
+/// to. Events with other ids pass through the listener as if it had no callback, so they aren't
+/// counted as reaching it, and it isn't in their [`ListenerInput::remaining_path`].
+#[derive(Default)]
+pub struct DynamicListeners {
+    callbacks: HashMap<DynamicEventId, CallbackSystem>,
+}
+
+impl DynamicListeners {
+    /// Run a callback system when an event with this id reaches the listener, replacing any
+    /// callback already added for it. The callback can access the event with
+    /// [`Listener<DynamicEvent>`](crate::callbacks::Listener), like any other [`On::run`] callback.
+    pub fn on<Marker>(
+        mut self,
+        id: DynamicEventId,
+        callback: impl IntoSystem<(), (), Marker>,
+    ) -> Self {
+        let callback = CallbackSystem::New(Box::new(IntoSystem::into_system(callback)));
+        self.callbacks.insert(id, callback);
+        self
+    }
+
+    /// Create the event listener.
+    pub fn build(self) -> On<DynamicEvent> {
+        let ids: HashSet<_> = self.callbacks.keys().copied().collect();
+        let mut callbacks = self.callbacks;
+        On::<DynamicEvent>::run(move |world: &mut World| {
+            let id = world.resource::<ListenerInput<DynamicEvent>>().id;
+            if let Some(callback) = callbacks.get_mut(&id) {
+                callback.run(world);
+            }
+        })
+        .filter(move |event| ids.contains(&event.id))
+    }
+}
@@ -1,28 +1,52 @@
 //! Provides the [`EventDispatcher`], which handles bubbling events through the entity hierarchy,
 //! and triggering event listeners.
 
-use bevy_ecs::prelude::*;
-use bevy_hierarchy::Parent;
-use bevy_utils::{HashMap, HashSet};
+use std::{
+    any::TypeId,
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    sync::{Arc, PoisonError},
+};
+
+use bevy_ecs::{
+    entity::Entities,
+    event::EventId,
+    prelude::*,
+    ptr::Ptr,
+    reflect::AppTypeRegistry,
+    system::{SystemParam, SystemState},
+};
+use bevy_hierarchy::{Children, Parent};
+use bevy_reflect::{ReflectFromPtr, ReflectFromReflect};
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+use bevy_utils::{Duration, HashMap, HashSet, Instant};
 
 use crate::{
-    callbacks::{CallbackSystem, ListenerInput},
-    event_listener::On,
+    callbacks::{
+        AnyListenerInput, Callback, ListenerBatch, ListenerCallback, ListenerInput,
+        ReadOnlyCallback, SharedSystems,
+    },
+    event_listener::{Broadcast, On, OnAny},
     EntityEvent,
 };
 
 /// Builds and executes the event listener callback graph.
 ///
-/// To traverse the entity hierarchy and read events without requiring callbacks implement `Clone`,
-/// we need to extract the callbacks out of their components before they can be run. This is because
-/// running callbacks requires mutable access to the [`World`], which we can't do if we are also
-/// trying to mutate the [`On`]'s inner callback state via `run` at the same time.
+/// Running callbacks requires mutable access to the [`World`], so the callbacks can't be run while
+/// they are borrowed from their [`On`] components. Instead, each [`On`] holds a shared handle to its
+/// callback, and the dispatcher moves the callback out of the handle and into the listener graph,
+/// without mutating the component itself.
+///
+/// The graph is kept across frames, because most entity hierarchies rarely change. Only the parts
+/// of the graph affected by changes to [`Parent`] or [`On<E>`] are discarded in
+/// [`EventDispatcher::cleanup`], and rebuilt the next time an event passes through them.
 #[derive(Resource)]
 pub struct EventDispatcher<E: EntityEvent> {
     /// All the events of type `E` that were emitted this frame, and encountered an [`On<E>`] while
-    /// traversing the entity hierarchy. The `Entity` in the tuple is the leaf node to use when
-    /// traversing the listener graph.
-    pub(crate) events: Vec<(E, Entity)>,
+    /// traversing the entity hierarchy. Events with several targets have an entry for each target.
+    pub(crate) events: Vec<PendingEvent<E>>,
     /// Traversing the entity hierarchy for each event can visit the same entity multiple times.
     /// Storing the callbacks for each of these potentially visited entities in a graph structure is
     /// necessary for a few reasons:
@@ -33,7 +57,176 @@ pub struct EventDispatcher<E: EntityEvent> {
     /// - This allows us to jump to the next listener in the hierarchy without unnecessary
     ///   traversal. When bubbling many events of the same type `E` through the same entity tree,
     ///   this can save a significant amount of work.
-    pub(crate) listener_graph: HashMap<Entity, (CallbackSystem, Option<Entity>)>,
+    ///
+    /// Each node also keeps the handle its callback was taken from, so the callback can be moved
+    /// back into its [`On<E>`] when the node is discarded.
+    pub(crate) listener_graph: ListenerGraph<E>,
+    /// The first listener at or above each entity that has been the target of an event, and the
+    /// number of ancestors of the target. If no listeners were found when traversing the entire
+    /// branch, the target belongs to a dead branch and is mapped to `None`, so other events do not
+    /// need to re-traverse it.
+    pub(crate) target_cache: HashMap<Entity, Option<(Entity, usize)>>,
+    /// Entities that had their [`On<E>`] or [`Parent`] removed since [`EventDispatcher::cleanup`]
+    /// last ran, recorded by [`EventDispatcher::track_removed`].
+    pub(crate) removed: Vec<Entity>,
+    /// Counters and timings collected while dispatching events. This is `None` unless something,
+    /// like the `EventListenerDiagnosticsPlugin`, has enabled it with
+    /// [`EventDispatcher::enable_stats`].
+    pub(crate) stats: Option<DispatchStats>,
+    /// Runs the listeners for the more general event types that events of type `E` are also
+    /// dispatched as, registered with
+    /// [`EventListenerPlugin::dispatch_as`](crate::EventListenerPlugin::dispatch_as).
+    pub(crate) generalizations: Vec<GeneralListener>,
+}
+
+/// An event waiting to bubble up from, or be broadcast down from, one of its targets.
+pub(crate) struct PendingEvent<E: EntityEvent> {
+    pub(crate) event: E,
+    /// The target to bubble up from.
+    pub(crate) target: Entity,
+    /// The leaf node to use when traversing the listener graph. Broadcast events do not use the
+    /// listener graph, so this is the target.
+    pub(crate) first_listener: Entity,
+    /// The number of ancestors of the target.
+    pub(crate) target_level: usize,
+    /// The position of the event in the order events were read. Entries for each target of the same
+    /// event share the same index.
+    pub(crate) index: usize,
+}
+
+/// The nodes of the listener graph, keyed by the listener entity.
+pub(crate) type ListenerGraph<E> = HashMap<Entity, ListenerNode<E>>;
+
+/// A listener in the [`ListenerGraph`].
+pub(crate) struct ListenerNode<E: EntityEvent> {
+    /// The callback taken from the listener's [`On<E>`].
+    pub(crate) callback: ListenerCallback<E>,
+    /// The next listener an event will bubble to after this one, if any.
+    pub(crate) next: Option<Entity>,
+    /// The number of ancestors of the listener entity.
+    pub(crate) level: usize,
+    /// The handle the callback was taken from, used to move it back when the node is discarded.
+    pub(crate) handle: Callback<E>,
+    /// Does the listener entity have an [`OnAny`]? Entities with an [`OnAny`] are added to the graph
+    /// even if they have no [`On<E>`], in which case the callback is empty.
+    pub(crate) any: bool,
+}
+
+/// Sent by the [`EventDispatcher`] in place of dispatching an event, when one of its targets no
+/// longer exists, e.g. because it was despawned after the event was sent. Events with several
+/// targets send one of these for each target that does not exist, and are still dispatched to the
+/// others.
+///
+/// These can be read like any other event to log, retry, or debug systems that target stale
+/// entities:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::{event_dispatcher::DeadTargetEvent, prelude::*};
+/// # #[derive(Clone, Event, EntityEvent)]
+/// # struct Attack(#[target] Entity);
+/// fn log_dead_targets(mut dead_targets: EventReader<DeadTargetEvent<Attack>>) {
+///     for dead in dead_targets.read() {
+///         warn!("Attack targeted {:?}, which no longer exists", dead.target);
+///     }
+/// }
+/// ```
+#[derive(Event, Clone, Debug)]
+pub struct DeadTargetEvent<E: EntityEvent> {
+    /// The event that could not be dispatched.
+    pub event: E,
+    /// The target that no longer exists.
+    pub target: Entity,
+}
+
+/// A [`SystemParam`] for sending entity events that are retargeted to the nearest surviving
+/// ancestor of their target, if the target is despawned before the event is dispatched.
+///
+/// Sending an event records the ancestors of each of its targets at that moment. When the event is
+/// dispatched, a target that no longer exists is replaced by the closest of these ancestors that
+/// still exists, which becomes the [`ListenerInput::target`] of the event. The event data itself is
+/// unchanged. If none of the ancestors exist, a [`DeadTargetEvent`] is sent instead. Events sent
+/// with an [`EventWriter`] are never retargeted.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::{event_dispatcher::RetargetingEventWriter, prelude::*};
+/// # #[derive(Clone, Event, EntityEvent)]
+/// # #[can_bubble]
+/// # struct Destroyed(#[target] Entity);
+/// # #[derive(Component)]
+/// # struct Health(u32);
+/// fn destroy(
+///     mut commands: Commands,
+///     mut destroyed: RetargetingEventWriter<Destroyed>,
+///     parts: Query<(Entity, &Health)>,
+/// ) {
+///     for (part, health) in &parts {
+///         if health.0 == 0 {
+///             // The part's ancestors are still notified, even though it is despawned first.
+///             destroyed.send(Destroyed(part));
+///             commands.entity(part).despawn();
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct RetargetingEventWriter<'w, 's, E: EntityEvent> {
+    events: EventWriter<'w, E>,
+    parents: Query<'w, 's, &'static Parent>,
+    sent_ancestry: ResMut<'w, SentAncestry<E>>,
+}
+
+impl<'w, 's, E: EntityEvent> RetargetingEventWriter<'w, 's, E> {
+    /// Send an event, recording the ancestors of its targets. Returns the id of the event.
+    pub fn send(&mut self, event: E) -> EventId<E> {
+        let ancestry = event
+            .targets()
+            .map(|target| {
+                let ancestors = std::iter::successors(self.parents.get(target).ok(), |parent| {
+                    self.parents.get(parent.get()).ok()
+                });
+                (target, ancestors.map(Parent::get).collect())
+            })
+            .collect();
+        let id = self.events.send(event);
+        self.sent_ancestry.0.insert(id, ancestry);
+        id
+    }
+}
+
+/// The ancestors of each target of the events sent with a [`RetargetingEventWriter`], from the
+/// parent up, recorded when the event was sent. Entries are removed when the event is read by
+/// [`EventDispatcher::build`].
+#[derive(Resource)]
+pub struct SentAncestry<E: EntityEvent>(HashMap<EventId<E>, Vec<(Entity, Vec<Entity>)>>);
+
+impl<E: EntityEvent> Default for SentAncestry<E> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+/// Counters and timings for the work done by an [`EventDispatcher`], accumulated until they are
+/// taken with [`EventDispatcher::take_stats`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DispatchStats {
+    /// Number of events read by the dispatcher.
+    pub events_received: usize,
+    /// Number of events that were dropped because no listeners exist in the target's branch of the
+    /// hierarchy.
+    pub events_dead_branch: usize,
+    /// Number of callbacks that were run.
+    pub listeners_invoked: usize,
+    /// Number of nodes added to the listener graph. Nodes are cached across frames, so this only
+    /// counts nodes that were not already in the graph.
+    pub graph_nodes_built: usize,
+    /// Time spent in [`EventDispatcher::build`].
+    pub build_time: Duration,
+    /// Time spent in [`EventDispatcher::bubble_events`], including running callbacks.
+    pub bubble_time: Duration,
+    /// Time spent in [`EventDispatcher::cleanup`], invalidating parts of the cached listener graph.
+    pub cleanup_time: Duration,
 }
 
 impl<E: EntityEvent> EventDispatcher<E> {
@@ -42,154 +235,1327 @@ impl<E: EntityEvent> EventDispatcher<E> {
     /// the entities with event listeners are included.
     pub fn build(
         mut events: EventReader<E>,
-        mut listeners: Query<(Option<&mut On<E>>, Option<&Parent>)>,
+        listeners: Query<ListenerQuery<E>>,
         mut dispatcher: ResMut<EventDispatcher<E>>,
-        mut dead_branch_nodes: Local<HashSet<Entity>>,
-        mut target_cache: Local<HashMap<Entity, Entity>>,
+        mut dead_targets: EventWriter<DeadTargetEvent<E>>,
+        mut sent_ancestry: ResMut<SentAncestry<E>>,
     ) {
+        #[cfg(feature = "trace")]
+        let _span =
+            info_span!("EventDispatcher::build", event = std::any::type_name::<E>()).entered();
+
+        let start = dispatcher.stats.is_some().then(Instant::now);
+        let nodes_before = dispatcher.listener_graph.len();
+
         // Reuse allocated memory
         dispatcher.events.clear();
-        dispatcher.listener_graph.clear();
-        dead_branch_nodes.clear();
-        target_cache.clear();
 
-        for event in events.read() {
-            // if the target belongs to a dead branch, exit early.
-            if dead_branch_nodes.contains(&event.target()) {
-                continue;
+        let mut events_received = 0;
+        let mut events_dead_branch = 0;
+        for (index, (event, id)) in events.read_with_id().enumerate() {
+            events_received += 1;
+            let ancestry = if sent_ancestry.0.is_empty() {
+                None
+            } else {
+                sent_ancestry.0.remove(&id)
+            };
+            for target in event.targets() {
+                // Every entity matches the query, so this only fails if the target was despawned.
+                let target = if listeners.contains(target) {
+                    target
+                } else {
+                    // Events sent with a `RetargetingEventWriter` start from the nearest ancestor of
+                    // the target that still exists, using the ancestry recorded when they were sent.
+                    let ancestor = ancestry
+                        .iter()
+                        .flatten()
+                        .find(|(entity, _)| *entity == target)
+                        .and_then(|(_, ancestors)| {
+                            ancestors.iter().copied().find(|e| listeners.contains(*e))
+                        });
+                    let Some(ancestor) = ancestor else {
+                        dead_targets.send(DeadTargetEvent {
+                            event: event.to_owned(),
+                            target,
+                        });
+                        continue;
+                    };
+                    ancestor
+                };
+                if event.broadcast().is_some() || !dispatcher.generalizations.is_empty() {
+                    // Broadcasts, and events that are also dispatched as more general event types,
+                    // walk the hierarchy while they are dispatched, so only the level of the target
+                    // is needed to order them with batch listeners.
+                    let Ok((_, parent, _)) = listeners.get(target) else {
+                        continue;
+                    };
+                    let ancestors = std::iter::successors(parent.map(Parent::get), |entity| {
+                        listeners.get(*entity).ok()?.1.map(Parent::get)
+                    });
+                    dispatcher.events.push(PendingEvent {
+                        event: event.to_owned(),
+                        target,
+                        first_listener: target,
+                        target_level: ancestors.count(),
+                        index,
+                    });
+                    continue;
+                }
+                // If the target has already been used to traverse the graph, use the cached value.
+                let first_listener = match dispatcher.target_cache.get(&target) {
+                    Some(first_listener) => *first_listener,
+                    None => build_branch_depth_first(target, &mut dispatcher, &listeners),
+                };
+                match first_listener {
+                    // Events that cannot bubble only interact with a listener on the target itself.
+                    Some((first_listener, target_level))
+                        if event.can_bubble() || first_listener == target =>
+                    {
+                        dispatcher.events.push(PendingEvent {
+                            event: event.to_owned(),
+                            target,
+                            first_listener,
+                            target_level,
+                            index,
+                        });
+                    }
+                    Some(_) => (),
+                    None => {
+                        if dispatcher.target_cache.contains_key(&target) {
+                            events_dead_branch += 1;
+                        }
+                    }
+                }
             }
-            // if the target has already been used to traverse the graph, use the cached value.
-            if let Some(first_listener) = target_cache.get(&event.target()) {
-                dispatcher.events.push((event.to_owned(), *first_listener));
-                continue;
+        }
+
+        let graph_nodes_built = dispatcher.listener_graph.len() - nodes_before;
+        if let (Some(stats), Some(start)) = (dispatcher.stats.as_mut(), start) {
+            stats.events_received += events_received;
+            stats.events_dead_branch += events_dead_branch;
+            stats.graph_nodes_built += graph_nodes_built;
+            stats.build_time += start.elapsed();
+        }
+    }
+
+    /// Records entities that had their [`On<E>`], [`OnAny`], or [`Parent`] removed, to be handled by
+    /// [`EventDispatcher::cleanup`].
+    ///
+    /// Removals can only be read for a short time after they happen, so unlike the other systems,
+    /// this runs every frame, even when no events are sent. Changes, on the other hand, are found
+    /// with change detection the next time `cleanup` runs.
+    pub fn track_removed(
+        mut dispatcher: ResMut<EventDispatcher<E>>,
+        mut removed_listeners: RemovedComponents<On<E>>,
+        mut removed_any_listeners: RemovedComponents<OnAny>,
+        mut removed_parents: RemovedComponents<Parent>,
+    ) {
+        let removed = removed_listeners
+            .read()
+            .chain(removed_any_listeners.read())
+            .chain(removed_parents.read());
+        if dispatcher.listener_graph.is_empty() && dispatcher.target_cache.is_empty() {
+            // Nothing is cached, so there is nothing to invalidate.
+            removed.for_each(drop);
+        } else {
+            // Avoid triggering change detection unless something was removed.
+            let mut removed = removed.peekable();
+            if removed.peek().is_some() {
+                dispatcher.removed.extend(removed);
             }
-            build_branch_depth_first(
-                event,
-                &mut dispatcher,
-                &mut listeners,
-                &mut dead_branch_nodes,
-                &mut target_cache,
-            );
         }
     }
 
-    /// Once we are done bubbling, we need to add the callback systems back into the components we
-    /// moved them from when building the tree.
-    pub fn cleanup(mut listeners: Query<&mut On<E>>, mut callbacks: ResMut<EventDispatcher<E>>) {
-        for (entity, (callback, _)) in callbacks.listener_graph.drain() {
-            if let Ok(mut listener) = listeners.get_mut(entity) {
-                // Do not restore the callback if it has been replaced by the event handler.
-                if listener.callback.is_empty() {
-                    listener.callback = callback;
-                }
+    /// Discards the parts of the cached listener graph that are no longer valid because an
+    /// [`On<E>`] or [`OnAny`] was added, changed, or removed, or an entity's [`Parent`] was removed,
+    /// so they will be rebuilt when an event passes through them.
+    ///
+    /// Changes to [`Parent`] affect the graphs of every event type, so they are found once per
+    /// frame for all event types by `invalidate_listener_graphs`.
+    pub fn cleanup(
+        mut dispatcher: ResMut<EventDispatcher<E>>,
+        changed_listeners: Query<Entity, ChangedListener<E>>,
+        children: Query<&Children>,
+        entities: &Entities,
+    ) {
+        #[cfg(feature = "trace")]
+        let _span = info_span!(
+            "EventDispatcher::cleanup",
+            event = std::any::type_name::<E>()
+        )
+        .entered();
+
+        let start = dispatcher.stats.is_some().then(Instant::now);
+        let dispatcher = dispatcher.as_mut();
+        if dispatcher.is_cache_empty() {
+            return;
+        }
+
+        let mut changed: Vec<Entity> = changed_listeners.iter().collect();
+        let mut entity_despawned = false;
+        for entity in dispatcher.removed.drain(..) {
+            if entities.contains(entity) {
+                changed.push(entity);
+            } else {
+                entity_despawned = true;
             }
         }
+
+        dispatcher.invalidate(&changed, entity_despawned, |entity| {
+            children.get(entity).map(|c| &**c).unwrap_or_default()
+        });
+
+        if let (Some(stats), Some(start)) = (dispatcher.stats.as_mut(), start) {
+            stats.cleanup_time += start.elapsed();
+        }
     }
 
     /// Bubbles [`EntityEvent`]s up the entity hierarchy, running  callbacks.
+    ///
+    /// Events are bubbled one at a time, in the order they were sent, until they reach a batch
+    /// listener. Events wait at batch listeners until every other event has gone as far as it can,
+    /// then batches are run starting from the deepest listener in the graph, so a batch contains
+    /// every event that will reach it this frame. Events that continue propagating from a batch
+    /// listener are bubbled one at a time again.
+    ///
+    /// Read-only callbacks are queued instead of being run immediately, and the queue is run
+    /// concurrently before the next callback that needs exclusive access to the world, or the next
+    /// read-only callback for an event that is already in the queue.
+    ///
+    /// [Broadcast](EntityEvent::broadcast) events are dispatched in the same order as other events,
+    /// but walk down through the descendants of their target instead, without using the listener
+    /// graph. Events of types that are also dispatched as more general event types walk up through
+    /// the ancestors of their target without using the listener graph, so they can reach listeners
+    /// for the general types.
     pub fn bubble_events(world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = info_span!(
+            "EventDispatcher::bubble_events",
+            event = std::any::type_name::<E>()
+        )
+        .entered();
+
         world.resource_scope(|world, mut dispatcher: Mut<EventDispatcher<E>>| {
             let dispatcher = dispatcher.as_mut();
-            dispatcher.events.drain(..).for_each(|(event_data, leaf)| {
-                let mut listener = leaf;
-                let can_bubble = event_data.can_bubble();
-
-                world.insert_resource(ListenerInput {
-                    listener,
-                    event_data,
-                    propagate: true,
-                });
-                while let Some((callback, next_node)) = dispatcher.listener_graph.get_mut(&listener)
-                {
-                    world.resource_mut::<ListenerInput<E>>().listener = listener;
-                    callback.run(world);
-                    if !can_bubble || !world.resource::<ListenerInput<E>>().propagate {
-                        break;
+            let start = dispatcher.stats.is_some().then(Instant::now);
+            let graph = &mut dispatcher.listener_graph;
+            let generals = &dispatcher.generalizations[..];
+            let mut batches = PendingBatches::default();
+            let mut read_only = PendingReadOnly::default();
+            let mut shared_systems = SharedSystems::default();
+            // The path from each leaf, and whether it has listeners with a filter.
+            let mut paths = HashMap::<Entity, (Arc<[(Entity, usize, bool)]>, bool)>::new();
+            let mut listeners_invoked = 0;
+            // Listeners already visited by other targets of the current event.
+            let mut shared = (usize::MAX, HashSet::new());
+            dispatcher.events.drain(..).for_each(|pending| {
+                if pending.event.broadcast().is_some() {
+                    let input = ListenerInput {
+                        listener: pending.target,
+                        target: pending.target,
+                        event_data: pending.event,
+                        propagate: true,
+                        path: Arc::from([]),
+                        index: 0,
+                        target_level: pending.target_level,
+                        depth: 0,
+                        skip_descendants: false,
+                    };
+                    listeners_invoked += broadcast_from(
+                        world,
+                        graph,
+                        input,
+                        generals,
+                        &mut batches,
+                        &mut read_only,
+                        &mut shared_systems,
+                    );
+                    return;
+                }
+                let leaf = pending.first_listener;
+                let (mut path, filtered) = paths
+                    .entry(leaf)
+                    .or_insert_with(|| {
+                        let path = match generals.is_empty() {
+                            true => std::iter::successors(Some(leaf), |node| graph.get(node)?.next)
+                                .filter_map(|entity| {
+                                    let node = graph.get(&entity)?;
+                                    Some((entity, node.level, !node.callback.is_empty()))
+                                })
+                                .collect(),
+                            false => hierarchy_path::<E>(
+                                world,
+                                leaf,
+                                pending.target_level,
+                                generals,
+                                None,
+                            ),
+                        };
+                        let filtered = path.iter().any(|&(entity, _, _)| {
+                            world
+                                .get::<On<E>>(entity)
+                                .is_some_and(|on| on.callback.is_filtered())
+                        });
+                        (path, filtered)
+                    })
+                    .clone();
+                if filtered {
+                    // Listeners with a filter only have a callback for the events it accepts.
+                    let event = &pending.event;
+                    path = match generals.is_empty() {
+                        true => path
+                            .iter()
+                            .map(|&(entity, level, callback)| {
+                                let accepts = graph
+                                    .get(&entity)
+                                    .is_none_or(|node| node.handle.accepts(event));
+                                (entity, level, callback && accepts)
+                            })
+                            .collect(),
+                        false => hierarchy_path::<E>(
+                            world,
+                            leaf,
+                            pending.target_level,
+                            generals,
+                            Some(event),
+                        ),
+                    };
+                }
+                if let Some(max_depth) = pending.event.max_depth() {
+                    let target_level = pending.target_level;
+                    let len = path
+                        .iter()
+                        .take_while(|(_, level, _)| target_level - level <= max_depth)
+                        .count();
+                    if len == 0 {
+                        return;
+                    } else if len < path.len() {
+                        path = path[..len].into();
+                    }
+                }
+                if !generals.is_empty() {
+                    let input = ListenerInput {
+                        listener: pending.target,
+                        target: pending.target,
+                        event_data: pending.event,
+                        propagate: true,
+                        path,
+                        index: 0,
+                        target_level: pending.target_level,
+                        depth: 0,
+                        skip_descendants: false,
+                    };
+                    listeners_invoked += bubble_through_hierarchy(
+                        world,
+                        graph,
+                        input,
+                        generals,
+                        &mut batches,
+                        &mut read_only,
+                        &mut shared_systems,
+                    );
+                    return;
+                }
+                if pending.event.deduplicate_listeners() {
+                    let (index, visited) = &mut shared;
+                    if *index != pending.index {
+                        *index = pending.index;
+                        visited.clear();
                     }
-                    match next_node {
-                        Some(next_node) => listener = *next_node,
-                        _ => break,
+                    // Leave the rest of the path to the bubble that already reached it.
+                    let reachable = match pending.event.can_bubble() {
+                        true => path.len(),
+                        false => 1,
+                    };
+                    let unvisited = path
+                        .iter()
+                        .take(reachable)
+                        .take_while(|(node, _, _)| visited.insert(*node));
+                    let len = unvisited.count();
+                    if len == 0 {
+                        return;
+                    } else if len < path.len() {
+                        path = path[..len].into();
                     }
                 }
-                world.remove_resource::<ListenerInput<E>>();
+                let input = ListenerInput {
+                    listener: leaf,
+                    target: pending.target,
+                    event_data: pending.event,
+                    propagate: true,
+                    path,
+                    index: 0,
+                    target_level: pending.target_level,
+                    depth: 0,
+                    skip_descendants: false,
+                };
+                listeners_invoked += bubble_from(
+                    world,
+                    graph,
+                    input,
+                    &mut batches,
+                    &mut read_only,
+                    &mut shared_systems,
+                );
             });
+            while let Some((listener, inputs)) = batches.pop_deepest() {
+                read_only.run(world, graph);
+                let next_node = graph.get(&listener).and_then(|node| node.next);
+                let Some((callback, handle)) = take_callback(world, graph, listener) else {
+                    continue;
+                };
+                let ListenerCallback::Batch(mut system) = callback else {
+                    put_callback(graph, listener, callback, handle);
+                    continue;
+                };
+                #[cfg(feature = "trace")]
+                let _span = info_span!(
+                    "batch callback",
+                    event = std::any::type_name::<E>(),
+                    ?listener,
+                    events = inputs.len()
+                )
+                .entered();
+                world.insert_resource(ListenerBatch { listener, inputs });
+                shared_systems.run(&mut system, world);
+                put_callback(graph, listener, ListenerCallback::Batch(system), handle);
+                listeners_invoked += 1;
+                let Some(batch) = world.remove_resource::<ListenerBatch<E>>() else {
+                    continue;
+                };
+                for mut input in batch.inputs {
+                    if !input.can_bubble() || !input.propagate {
+                        continue;
+                    }
+                    if !generals.is_empty() && input.broadcast().is_none() {
+                        // Events walking the hierarchy continue from the batch listener's parent.
+                        let Some(parent) = world.get::<Parent>(listener) else {
+                            continue;
+                        };
+                        input.listener = parent.get();
+                        input.index += 1;
+                        input.depth += 1;
+                        listeners_invoked += bubble_through_hierarchy(
+                            world,
+                            graph,
+                            input,
+                            generals,
+                            &mut batches,
+                            &mut read_only,
+                            &mut shared_systems,
+                        );
+                    } else if let Some(next_node) = next_node {
+                        if input.index + 1 < input.path.len() {
+                            input.listener = next_node;
+                            input.index += 1;
+                            listeners_invoked += bubble_from(
+                                world,
+                                graph,
+                                input,
+                                &mut batches,
+                                &mut read_only,
+                                &mut shared_systems,
+                            );
+                        }
+                    }
+                }
+            }
+            read_only.run(world, graph);
+            shared_systems.restore();
+            if let (Some(stats), Some(start)) = (dispatcher.stats.as_mut(), start) {
+                stats.listeners_invoked += listeners_invoked;
+                stats.bubble_time += start.elapsed();
+            }
+        });
+    }
+
+    /// Are there events that reached a listener waiting to be bubbled?
+    pub(crate) fn has_events(dispatcher: Res<Self>) -> bool {
+        !dispatcher.events.is_empty()
+    }
+
+    /// Is there nothing cached that could be invalidated?
+    fn is_cache_empty(&self) -> bool {
+        self.listener_graph.is_empty() && self.target_cache.is_empty()
+    }
+
+    /// Discard the cached graph for every path through `changed`, which includes the changed
+    /// entities and all of their descendants. If an entity was despawned, its descendants can no
+    /// longer be found, so the entire graph is discarded.
+    fn invalidate<'a>(
+        &mut self,
+        changed: &[Entity],
+        entity_despawned: bool,
+        children: impl Fn(Entity) -> &'a [Entity],
+    ) {
+        if entity_despawned {
+            self.target_cache.clear();
+            for (_, node) in self.listener_graph.drain() {
+                node.handle.restore(node.callback);
+            }
+            return;
+        }
+        let mut stack = changed.to_vec();
+        let mut visited = HashSet::new();
+        while let Some(entity) = stack.pop() {
+            if !visited.insert(entity) {
+                continue;
+            }
+            self.target_cache.remove(&entity);
+            if let Some(node) = self.listener_graph.remove(&entity) {
+                node.handle.restore(node.callback);
+            }
+            stack.extend(children(entity));
+        }
+    }
+
+    /// Start collecting [`DispatchStats`]. This has no effect if stats are already being collected.
+    pub fn enable_stats(&mut self) {
+        self.stats.get_or_insert_with(DispatchStats::default);
+    }
+
+    /// The stats collected since they were last taken, if stats are enabled.
+    pub fn stats(&self) -> Option<&DispatchStats> {
+        self.stats.as_ref()
+    }
+
+    /// Take the stats collected since they were last taken, resetting them to zero. Returns `None`
+    /// if stats are not enabled.
+    pub fn take_stats(&mut self) -> Option<DispatchStats> {
+        self.stats.as_mut().map(std::mem::take)
+    }
+}
+
+/// Bubble a single event up the listener graph, starting at `input.listener`, until it stops
+/// propagating or reaches a batch listener, where it is added to `batches`. Read-only callbacks are
+/// added to `read_only`, which is run before the next exclusive callback. Returns the number of
+/// callbacks that were run or queued.
+fn bubble_from<E: EntityEvent>(
+    world: &mut World,
+    graph: &mut ListenerGraph<E>,
+    input: ListenerInput<E>,
+    batches: &mut PendingBatches<E>,
+    read_only: &mut PendingReadOnly<E>,
+    shared_systems: &mut SharedSystems,
+) -> usize {
+    let mut listener = input.listener;
+    let mut index = input.index;
+    let can_bubble = input.can_bubble();
+    let path_len = input.path.len();
+    #[cfg(feature = "trace")]
+    let target = input.target();
+    let mut listeners_invoked = 0;
+
+    // Has the [`OnAny`] on the current listener been run?
+    let mut any_ran = false;
+    read_only.next_event();
+    world.insert_resource(input);
+    while let Some(node) = graph.get_mut(&listener) {
+        let next_node = node.next;
+        let level = node.level;
+        // Entities that only have an [`OnAny`] are in the graph with an empty callback, and
+        // listeners with a filter that rejects this event are treated like them.
+        let empty = node.callback.is_empty()
+            || (node.handle.is_filtered()
+                && !node.handle.accepts(world.resource::<ListenerInput<E>>()));
+        match &mut node.callback {
+            _ if node.any && !any_ran => {
+                read_only.run(world, graph);
+                let mut input = world.resource_mut::<ListenerInput<E>>();
+                input.listener = listener;
+                input.index = index;
+                input.depth = input.target_level.saturating_sub(level);
+                listeners_invoked += run_any_listener::<E>(world, listener);
+                any_ran = true;
+                continue;
+            }
+            _ if empty => {}
+            ListenerCallback::Exclusive(_) if !read_only.is_empty() => {
+                // Queued read-only callbacks must see the world before this callback changes it.
+                read_only.run(world, graph);
+                continue;
+            }
+            ListenerCallback::Exclusive(callback) => {
+                let mut input = world.resource_mut::<ListenerInput<E>>();
+                input.listener = listener;
+                input.index = index;
+                input.depth = input.target_level.saturating_sub(level);
+                #[cfg(feature = "trace")]
+                let _span = info_span!(
+                    "callback",
+                    event = std::any::type_name::<E>(),
+                    ?listener,
+                    ?target
+                )
+                .entered();
+                shared_systems.run(callback, world);
+                if !world.resource::<ListenerInput<E>>().propagate {
+                    break;
+                }
+            }
+            ListenerCallback::ReadOnly(_) => {
+                let mut input = world.resource::<ListenerInput<E>>().clone();
+                input.listener = listener;
+                input.index = index;
+                input.depth = input.target_level.saturating_sub(level);
+                read_only.push(world, graph, input);
+            }
+            ListenerCallback::Batch(_) => {
+                if let Some(mut input) = world.remove_resource::<ListenerInput<E>>() {
+                    input.listener = listener;
+                    input.index = index;
+                    input.depth = input.target_level.saturating_sub(level);
+                    batches.push(input, level);
+                }
+                return listeners_invoked;
+            }
+        }
+        if !empty {
+            listeners_invoked += 1;
+        }
+        match next_node {
+            Some(next_node) if can_bubble && index + 1 < path_len => {
+                listener = next_node;
+                index += 1;
+                any_ran = false;
+            }
+            _ => break,
+        }
+    }
+    world.remove_resource::<ListenerInput<E>>();
+    listeners_invoked
+}
+
+/// Broadcast a single event down the entity hierarchy, starting at `input.target`, visiting every
+/// descendant in the order given by [`EntityEvent::broadcast`] until it stops propagating.
+/// Listeners that skip their descendants prune their subtree from the broadcast. Read-only and
+/// batch callbacks are queued like they are when bubbling, and can't prune or stop the broadcast.
+/// Returns the number of callbacks that were run or queued.
+fn broadcast_from<E: EntityEvent>(
+    world: &mut World,
+    graph: &mut ListenerGraph<E>,
+    input: ListenerInput<E>,
+    generals: &[GeneralListener],
+    batches: &mut PendingBatches<E>,
+    read_only: &mut PendingReadOnly<E>,
+    shared_systems: &mut SharedSystems,
+) -> usize {
+    let broadcast = input.broadcast().unwrap_or(Broadcast::BreadthFirst);
+    let target_level = input.target_level;
+    let max_depth = input.max_depth().unwrap_or(usize::MAX);
+    let mut index = input.index;
+    let mut listeners_invoked = 0;
+    // Entities waiting to be visited, with how many levels they are below the target.
+    let mut pending = VecDeque::from([(input.target, 0)]);
+
+    read_only.next_event();
+    world.insert_resource(input);
+    while let Some((entity, depth)) = match broadcast {
+        Broadcast::BreadthFirst => pending.pop_front(),
+        Broadcast::DepthFirst => pending.pop_back(),
+    } {
+        let mut input = world.resource_mut::<ListenerInput<E>>();
+        input.listener = entity;
+        input.index = index;
+        input.depth = depth;
+        input.skip_descendants = false;
+        let level = target_level + depth;
+        let (invoked, _) = visit_entity(
+            world,
+            graph,
+            level,
+            generals,
+            batches,
+            read_only,
+            shared_systems,
+        );
+        if invoked > 0 {
+            index += 1;
+            listeners_invoked += invoked;
+        }
+        let input = world.resource::<ListenerInput<E>>();
+        if !input.propagate {
+            break;
+        }
+        let visit_children = !input.skip_descendants && depth < max_depth;
+        if let Some(children) = world.get::<Children>(entity).filter(|_| visit_children) {
+            let children = children.iter().map(|&child| (child, depth + 1));
+            match broadcast {
+                Broadcast::BreadthFirst => pending.extend(children),
+                // Children are pushed in reverse, so the first child is popped next.
+                Broadcast::DepthFirst => pending.extend(children.rev()),
+            }
+        }
+    }
+    world.remove_resource::<ListenerInput<E>>();
+    listeners_invoked
+}
+
+/// Bubble a single event up the entity hierarchy, starting at `input.listener`, by walking through
+/// each entity's [`Parent`] instead of the listener graph, until it stops propagating or reaches a
+/// batch listener, where it is added to `batches`. This is used for events that are also dispatched
+/// as more general event types, whose listeners are not in the listener graph. The path of the
+/// event is found with [`hierarchy_path`] before it starts bubbling. Returns the number of callbacks
+/// that were run or queued.
+fn bubble_through_hierarchy<E: EntityEvent>(
+    world: &mut World,
+    graph: &mut ListenerGraph<E>,
+    input: ListenerInput<E>,
+    generals: &[GeneralListener],
+    batches: &mut PendingBatches<E>,
+    read_only: &mut PendingReadOnly<E>,
+    shared_systems: &mut SharedSystems,
+) -> usize {
+    let can_bubble = input.can_bubble();
+    let max_depth = input.max_depth().unwrap_or(usize::MAX);
+    let target_level = input.target_level;
+    let path = input.path.clone();
+    let mut entity = input.listener;
+    let mut depth = input.depth;
+    let mut index = input.index;
+    let mut listeners_invoked = 0;
+
+    read_only.next_event();
+    world.insert_resource(input);
+    while depth <= max_depth {
+        let mut input = world.resource_mut::<ListenerInput<E>>();
+        input.listener = entity;
+        input.index = index;
+        input.depth = depth;
+        let level = target_level.saturating_sub(depth);
+        let (invoked, waiting) = visit_entity(
+            world,
+            graph,
+            level,
+            generals,
+            batches,
+            read_only,
+            shared_systems,
+        );
+        listeners_invoked += invoked;
+        if path
+            .get(index)
+            .is_some_and(|&(listener, _, _)| listener == entity)
+        {
+            index += 1;
+        }
+        if waiting {
+            break;
+        }
+        let propagate = world.resource::<ListenerInput<E>>().propagate;
+        match world.get::<Parent>(entity) {
+            Some(parent) if propagate && can_bubble => {
+                entity = parent.get();
+                depth += 1;
+            }
+            _ => break,
+        }
+    }
+    world.remove_resource::<ListenerInput<E>>();
+    listeners_invoked
+}
+
+/// The path of an event walking the hierarchy from `target`, for [`ListenerInput::path`]: every
+/// ancestor of the target, and the target itself, with an [`On<E>`], an [`OnAny`], or an [`On<G>`]
+/// for one of the general event types in `generals`, the number of ancestors of each, and whether
+/// it has a callback for the event other than its [`OnAny`]. Listeners with a filter are only
+/// treated as having a callback if the filter accepts `event`, or if there is no `event` to check.
+fn hierarchy_path<E: EntityEvent>(
+    world: &World,
+    target: Entity,
+    target_level: usize,
+    generals: &[GeneralListener],
+    event: Option<&E>,
+) -> Arc<[(Entity, usize, bool)]> {
+    let has_callback = |entity| {
+        world.get::<On<E>>(entity).is_some_and(|on| {
+            !on.callback.is_empty() && event.is_none_or(|event| on.callback.accepts(event))
+        }) || generals
+            .iter()
+            .any(|general| (general.has_listener)(world, entity))
+    };
+    std::iter::successors(Some(target), |&entity| {
+        world.get::<Parent>(entity).map(Parent::get)
+    })
+    .zip(0..)
+    .map(|(entity, depth)| (entity, depth, has_callback(entity)))
+    .filter(|&(entity, _, callback)| callback || world.get::<OnAny>(entity).is_some())
+    .map(|(entity, depth, callback)| (entity, target_level.saturating_sub(depth), callback))
+    .collect()
+}
+
+/// Run the listeners on the current listener of the [`ListenerInput`] in the world, for an event
+/// that is walking the hierarchy instead of the listener graph: its [`OnAny`], its [`On<E>`], then
+/// the listeners for the general event types in `generals`. The [`ListenerInput`] must already be
+/// updated for this entity, and `level` is the number of ancestors of the entity. Returns the
+/// number of callbacks that were run or queued, and whether the event is waiting at a batch
+/// listener.
+fn visit_entity<E: EntityEvent>(
+    world: &mut World,
+    graph: &mut ListenerGraph<E>,
+    level: usize,
+    generals: &[GeneralListener],
+    batches: &mut PendingBatches<E>,
+    read_only: &mut PendingReadOnly<E>,
+    shared_systems: &mut SharedSystems,
+) -> (usize, bool) {
+    let entity = world.resource::<ListenerInput<E>>().listener;
+    let mut listeners_invoked = 0;
+    let mut waiting = false;
+    let mut queued = None;
+    if world.get::<OnAny>(entity).is_some() {
+        read_only.run(world, graph);
+        listeners_invoked += run_any_listener::<E>(world, entity);
+    }
+    let accepts = world
+        .get::<On<E>>(entity)
+        .is_none_or(|on| on.callback.accepts(world.resource::<ListenerInput<E>>()));
+    // Taking an empty callback leaves an empty one behind, so it doesn't need to be put back.
+    let callback = take_callback(world, graph, entity).filter(|_| accepts);
+    if let Some((callback, handle)) = callback.filter(|(callback, _)| !callback.is_empty()) {
+        let callback = match callback {
+            ListenerCallback::Exclusive(mut system) => {
+                // Queued read-only callbacks must see the world before this callback changes it.
+                read_only.run(world, graph);
+                #[cfg(feature = "trace")]
+                let _span = info_span!(
+                    "callback",
+                    event = std::any::type_name::<E>(),
+                    listener = ?entity,
+                    target = ?world.resource::<ListenerInput<E>>().target()
+                )
+                .entered();
+                shared_systems.run(&mut system, world);
+                ListenerCallback::Exclusive(system)
+            }
+            ListenerCallback::ReadOnly(system) => {
+                // This is queued once the callback is back, in case the queue has to be run first.
+                queued = Some(world.resource::<ListenerInput<E>>().clone());
+                ListenerCallback::ReadOnly(system)
+            }
+            ListenerCallback::Batch(system) => {
+                batches.push(world.resource::<ListenerInput<E>>().clone(), level);
+                waiting = true;
+                ListenerCallback::Batch(system)
+            }
+        };
+        put_callback(graph, entity, callback, handle);
+        listeners_invoked += 1;
+    }
+    if let Some(input) = queued {
+        read_only.push(world, graph, input);
+    }
+    if !generals.is_empty() {
+        read_only.run(world, graph);
+        for general in generals {
+            listeners_invoked += (general.run)(world, shared_systems);
+        }
+    }
+    (listeners_invoked, waiting)
+}
+
+/// The listeners for an event type `E` that is also dispatched as the more general event type `G`.
+#[derive(Clone, Copy)]
+pub(crate) struct GeneralListener {
+    /// Runs the [`On<G>`] listener, if any, on the current listener of the [`ListenerInput<E>`] in
+    /// the world. Returns the number of callbacks that were run.
+    run: fn(&mut World, &mut SharedSystems) -> usize,
+    /// Does the entity have an [`On<G>`] listener?
+    has_listener: fn(&World, Entity) -> bool,
+}
+
+impl GeneralListener {
+    /// The [`GeneralListener`] for events of type `E` dispatched as `G`.
+    pub(crate) fn new<E, G>() -> Self
+    where
+        E: EntityEvent,
+        G: EntityEvent + From<E>,
+    {
+        Self {
+            run: run_general_listener::<E, G>,
+            has_listener: |world, entity| world.get::<On<G>>(entity).is_some(),
+        }
+    }
+}
+
+/// Runs the [`On<G>`] listener for events of type `E` dispatched as `G`. The listener receives a
+/// [`ListenerInput<G>`] converted from the event, with the same path, and stopping its propagation,
+/// or skipping the descendants of a broadcast, also applies to the original event.
+fn run_general_listener<E, G>(world: &mut World, shared_systems: &mut SharedSystems) -> usize
+where
+    E: EntityEvent,
+    G: EntityEvent + From<E>,
+{
+    let input = world.resource::<ListenerInput<E>>();
+    let listener = input.listener;
+    let Some(on) = world.get::<On<G>>(listener) else {
+        return 0;
+    };
+    let handle = on.callback.clone();
+    if !world.contains_resource::<EventDispatcher<G>>() {
+        return 0;
+    }
+    let general = ListenerInput {
+        listener,
+        target: input.target,
+        event_data: G::from(input.event_data.clone()),
+        propagate: input.propagate,
+        path: input.path.clone(),
+        index: input.index,
+        target_level: input.target_level,
+        depth: input.depth,
+        skip_descendants: input.skip_descendants,
+    };
+    world.resource_scope(|world, mut dispatcher: Mut<EventDispatcher<G>>| {
+        let graph = &mut dispatcher.listener_graph;
+        // The graph for `G` is only cleaned up on frames with events of type `G`, so it can hold
+        // the callback of an `On<G>` that has since been replaced.
+        let (callback, handle) = match graph.get_mut(&listener) {
+            Some(node) if node.handle.ptr_eq(&handle) => (std::mem::take(&mut node.callback), None),
+            _ => (handle.take(), Some(handle)),
+        };
+        #[cfg(feature = "trace")]
+        let _span = info_span!(
+            "callback",
+            event = std::any::type_name::<G>(),
+            ?listener,
+            target = ?general.target
+        )
+        .entered();
+        let (callback, invoked) = match callback {
+            ListenerCallback::Exclusive(mut system) => {
+                world.insert_resource(general);
+                shared_systems.run(&mut system, world);
+                if let Some(general) = world.remove_resource::<ListenerInput<G>>() {
+                    let mut input = world.resource_mut::<ListenerInput<E>>();
+                    input.propagate &= general.propagate;
+                    input.skip_descendants |= general.skip_descendants;
+                }
+                (ListenerCallback::Exclusive(system), 1)
+            }
+            ListenerCallback::ReadOnly(mut system) => {
+                system.initialize(world);
+                system.run(general, world);
+                system.apply_deferred(world);
+                (ListenerCallback::ReadOnly(system), 1)
+            }
+            // Batch listeners only receive events of their own type.
+            callback => (callback, 0),
+        };
+        put_callback(graph, listener, callback, handle);
+        invoked
+    })
+}
+
+/// Run the [`OnAny`] callback of `listener` with a type-erased view of the [`ListenerInput`] in the
+/// world. Returns the number of callbacks that were run.
+fn run_any_listener<E: EntityEvent>(world: &mut World, listener: Entity) -> usize {
+    let Some(callback) = world.get::<OnAny>(listener).map(|any| any.callback.clone()) else {
+        return 0;
+    };
+    let input = world.resource::<ListenerInput<E>>();
+    let event = world
+        .get_resource::<AppTypeRegistry>()
+        .and_then(|registry| {
+            let registry = registry.read();
+            let from_ptr = registry.get_type_data::<ReflectFromPtr>(TypeId::of::<E>())?;
+            // SAFETY: the type data was registered for `E`, and the pointer is to an `E`.
+            let event = unsafe { from_ptr.as_reflect(Ptr::from(&input.event_data)) };
+            // Prefer a concrete copy of the event that can be downcast, over a dynamic one.
+            let from_reflect = registry.get_type_data::<ReflectFromReflect>(TypeId::of::<E>());
+            Some(
+                from_reflect
+                    .and_then(|from_reflect| from_reflect.from_reflect(event))
+                    .unwrap_or_else(|| event.clone_value()),
+            )
         });
+    let input = AnyListenerInput {
+        event_type: std::any::type_name::<E>(),
+        listener,
+        target: input.target,
+        event,
+    };
+    #[cfg(feature = "trace")]
+    let _span = info_span!(
+        "any callback",
+        event = std::any::type_name::<E>(),
+        ?listener,
+        target = ?input.target
+    )
+    .entered();
+    world.insert_resource(input);
+    callback
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .run(world);
+    world.remove_resource::<AnyListenerInput>();
+    1
+}
+
+/// Take the callback of `listener` out of the listener graph to run it. Listeners that are only
+/// reached by broadcasts are not in the graph, so their callback is taken out of their [`On<E>`]
+/// instead, and the handle it was taken from is returned with it.
+fn take_callback<E: EntityEvent>(
+    world: &World,
+    graph: &mut ListenerGraph<E>,
+    listener: Entity,
+) -> Option<(ListenerCallback<E>, Option<Callback<E>>)> {
+    if let Some(node) = graph.get_mut(&listener) {
+        return Some((std::mem::take(&mut node.callback), None));
+    }
+    let handle = world.get::<On<E>>(listener)?.callback.clone();
+    Some((handle.take(), Some(handle)))
+}
+
+/// Put a callback taken with [`take_callback`] back where it came from.
+fn put_callback<E: EntityEvent>(
+    graph: &mut ListenerGraph<E>,
+    listener: Entity,
+    callback: ListenerCallback<E>,
+    handle: Option<Callback<E>>,
+) {
+    match handle {
+        Some(handle) => handle.restore(callback),
+        None => {
+            if let Some(node) = graph.get_mut(&listener) {
+                node.callback = callback;
+            }
+        }
+    }
+}
+
+/// Read-only callbacks waiting to be run, grouped by listener in the order they were queued.
+struct PendingReadOnly<E: EntityEvent> {
+    listeners: Vec<(Entity, Vec<ListenerInput<E>>)>,
+    index: HashMap<Entity, usize>,
+    /// Has the event currently being bubbled or broadcast queued a callback since the queue was
+    /// last run?
+    event_queued: bool,
+}
+
+impl<E: EntityEvent> Default for PendingReadOnly<E> {
+    fn default() -> Self {
+        Self {
+            listeners: Vec::new(),
+            index: HashMap::new(),
+            event_queued: false,
+        }
+    }
+}
+
+impl<E: EntityEvent> PendingReadOnly<E> {
+    fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    /// Start queueing callbacks for another event.
+    fn next_event(&mut self) {
+        self.event_queued = false;
+    }
+
+    /// Queue the callback of `input.listener`. Different listeners run concurrently, so if the
+    /// current event already queued a callback, the queue is run first, to keep the callbacks for a
+    /// single event in the order the event reaches them.
+    fn push(&mut self, world: &mut World, graph: &mut ListenerGraph<E>, input: ListenerInput<E>) {
+        if self.event_queued {
+            self.run(world, graph);
+        }
+        self.event_queued = true;
+        let listener = input.listener;
+        let index = *self.index.entry(listener).or_insert_with(|| {
+            self.listeners.push((listener, Vec::new()));
+            self.listeners.len() - 1
+        });
+        self.listeners[index].1.push(input);
+    }
+
+    /// Run every queued callback on the [`ComputeTaskPool`]. Each listener's callback is run for
+    /// all of its events in order on a single task, while different listeners run concurrently.
+    /// Commands are applied afterwards, in the order the listeners were first queued.
+    fn run(&mut self, world: &mut World, graph: &mut ListenerGraph<E>) {
+        if self.is_empty() {
+            return;
+        }
+        self.index.clear();
+        self.event_queued = false;
+        let mut tasks: Vec<ReadOnlyTask<E>> = self
+            .listeners
+            .drain(..)
+            .filter_map(
+                |(listener, inputs)| match take_callback(world, graph, listener)? {
+                    (ListenerCallback::ReadOnly(read_only), handle) => {
+                        Some((listener, read_only, inputs, handle))
+                    }
+                    (other, handle) => {
+                        put_callback(graph, listener, other, handle);
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        for (_, callback, _, _) in &mut tasks {
+            callback.initialize(world);
+        }
+        let shared_world: &World = world;
+        ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+            for (_listener, callback, inputs, _) in &mut tasks {
+                scope.spawn(async move {
+                    #[cfg(feature = "trace")]
+                    let _span = info_span!(
+                        "read-only callback",
+                        event = std::any::type_name::<E>(),
+                        listener = ?_listener,
+                        events = inputs.len()
+                    )
+                    .entered();
+                    for input in inputs.drain(..) {
+                        callback.run(input, shared_world);
+                    }
+                });
+            }
+        });
+        for (listener, mut callback, _, handle) in tasks {
+            callback.apply_deferred(world);
+            put_callback(
+                graph,
+                listener,
+                ListenerCallback::ReadOnly(callback),
+                handle,
+            );
+        }
+    }
+}
+
+/// A read-only callback taken out to be run, with its events and the handle it was taken from, if
+/// it wasn't in the listener graph.
+type ReadOnlyTask<E> = (
+    Entity,
+    ReadOnlyCallback<E>,
+    Vec<ListenerInput<E>>,
+    Option<Callback<E>>,
+);
+
+/// Events waiting at batch listeners, and the order the batches should be run in.
+struct PendingBatches<E: EntityEvent> {
+    inputs: HashMap<Entity, Vec<ListenerInput<E>>>,
+    /// Batch listeners ordered by their level in the hierarchy, deepest first, then by the order
+    /// events first reached them.
+    order: BinaryHeap<(usize, Reverse<usize>, Entity)>,
+    /// The number of batches that have been added to `order`.
+    pushed: usize,
+}
+
+impl<E: EntityEvent> Default for PendingBatches<E> {
+    fn default() -> Self {
+        Self {
+            inputs: HashMap::new(),
+            order: BinaryHeap::new(),
+            pushed: 0,
+        }
+    }
+}
+
+impl<E: EntityEvent> PendingBatches<E> {
+    fn push(&mut self, input: ListenerInput<E>, level: usize) {
+        let listener = input.listener;
+        let inputs = self.inputs.entry(listener).or_default();
+        if inputs.is_empty() {
+            // Events only ever bubble towards listeners with a smaller level, so once the deepest
+            // batch is run, no more events can reach it.
+            self.order.push((level, Reverse(self.pushed), listener));
+            self.pushed += 1;
+        }
+        inputs.push(input);
+    }
+
+    fn pop_deepest(&mut self) -> Option<(Entity, Vec<ListenerInput<E>>)> {
+        let (_, _, listener) = self.order.pop()?;
+        Some((listener, self.inputs.remove(&listener).unwrap_or_default()))
     }
 }
 
 /// Build a branch of the event bubbling graph, starting from the target entity, traversing up the
 /// hierarchy through the parents. Any event listeners that are found during traversal will be added
-/// as nodes to the graph.
+/// as nodes to the graph. Returns the first listener at or above the target, if any, and the number
+/// of ancestors of the target.
+///
+/// The branch is always built up to the root, even for events that cannot bubble, so that the
+/// cached graph is correct for any event passing through it.
 fn build_branch_depth_first<E: EntityEvent>(
-    event: &E,
+    target: Entity,
     dispatcher: &mut ResMut<EventDispatcher<E>>,
-    listeners: &mut Query<(Option<&mut On<E>>, Option<&Parent>)>,
-    dead_branch_nodes: &mut HashSet<Entity>,
-    target_cache: &mut HashMap<Entity, Entity>,
-) {
+    listeners: &Query<ListenerQuery<E>>,
+) -> Option<(Entity, usize)> {
     let graph = &mut dispatcher.listener_graph;
-    let mut this_node = event.target();
-    let mut prev_node = None;
+    let mut prev_node: Option<Entity> = None;
     let mut first_listener = None;
+    let mut reached_surface = false;
+    // The nodes added to the graph, and how many levels they are above the target.
+    let mut new_nodes = Vec::new();
+    let mut height = 0;
+    // The level of the highest entity visited, and how many levels it is above the target.
+    let mut top = (0, 0);
 
-    loop {
-        if let Some((_, next_node)) = graph.get(&this_node) {
-            // If the current entity is already in the map, use it to jump ahead
-            if first_listener.is_none() {
-                first_listener = Some(this_node);
-            }
-            if prev_node.is_none() {
-                break; // We can break if we aren't in the middle of mapping a path
+    walk_branch(target, true, |this_node| {
+        let this_height = height;
+        height += 1;
+        if let Some(node) = graph.get(&this_node) {
+            // If the current entity is already in the map, the rest of the branch is known, and we
+            // only need to point the previous node to this node.
+            top = (node.level, this_height);
+            if let Some(prev_node) = prev_node.and_then(|e| graph.get_mut(&e)) {
+                prev_node.next = Some(this_node);
             }
-            match next_node {
-                Some(next_node) => this_node = *next_node,
-                None => break, // Bubble reached the surface!
-            }
-        } else if let Ok((event_listener, parent)) = listeners.get_mut(this_node) {
+            first_listener.get_or_insert(this_node);
+            reached_surface = true;
+            None
+        } else if let Ok((event_listener, parent, any)) = listeners.get(this_node) {
             // Otherwise, get the current entity's data with a query
-            if let Some(mut event_listener) = event_listener {
+            if event_listener.is_some() || any {
                 // If it has an event listener, we need to add it to the map
-                graph.insert(this_node, (event_listener.take(), None));
+                let handle = event_listener
+                    .map(|event_listener| event_listener.callback.clone())
+                    .unwrap_or_default();
+                let node = ListenerNode {
+                    callback: handle.take(),
+                    next: None,
+                    // The level is set once the top of the branch is found.
+                    level: 0,
+                    handle,
+                    any,
+                };
+                graph.insert(this_node, node);
                 // We must also point the previous node to this node
-                if let Some((_, prev_nodes_next_node @ None)) =
-                    prev_node.and_then(|e| graph.get_mut(&e))
-                {
-                    *prev_nodes_next_node = Some(this_node);
-                }
-                if first_listener.is_none() {
-                    first_listener = Some(this_node);
+                if let Some(prev_node) = prev_node.and_then(|e| graph.get_mut(&e)) {
+                    prev_node.next = Some(this_node);
                 }
+                first_listener.get_or_insert(this_node);
                 prev_node = Some(this_node);
+                new_nodes.push((this_node, this_height));
             }
-            match parent {
-                Some(parent) => this_node = **parent,
-                None => {
-                    if first_listener.is_none() {
-                        // No listeners were found when traversing the entire branch. To prevent
-                        // other events re-traversing this dead branch, we record the target as
-                        // belonging to a dead branch.
-                        dead_branch_nodes.insert(event.target());
-                    }
-                    break; // Bubble reached the surface!
-                }
+            top = (0, this_height);
+            if parent.is_none() {
+                reached_surface = true; // Bubble reached the surface!
             }
+            parent.map(Parent::get)
         } else {
-            // This branch can only be reached if the listeners.get_mut() call fails. Note that the
+            // This branch can only be reached if the listeners.get() call fails. Note that the
             // query allows all components to be optional, which means this can only fail if the
-            // entity no longer exists. This can happen if the entity targeted by the event was
+            // entity no longer exists. Targets that no longer exist are sent as a
+            // `DeadTargetEvent` before the branch is built, so this is an ancestor that was
             // deleted before the bubbling system could run.
-            break;
+            None
         }
+    });
 
-        if !event.can_bubble() {
-            break;
+    let (top_level, top_height) = top;
+    for (entity, height) in new_nodes {
+        if let Some(node) = graph.get_mut(&entity) {
+            node.level = top_level + top_height - height;
         }
     }
+    let first_listener =
+        first_listener.map(|first_listener| (first_listener, top_level + top_height));
+
+    // Only cache complete branches. If no listeners were found when traversing the entire branch,
+    // this records the target as belonging to a dead branch.
+    if reached_surface {
+        dispatcher.target_cache.insert(target, first_listener);
+    }
+    first_listener
+}
+
+/// The components of an entity used to build the listener graph.
+type ListenerQuery<E> = (Option<&'static On<E>>, Option<&'static Parent>, Has<OnAny>);
+
+/// Finds entities with an [`On<E>`] or [`OnAny`] that was added or changed.
+type ChangedListener<E> = Or<(Changed<On<E>>, Changed<OnAny>)>;
+
+/// Shared by the [`EventDispatcher`]s of every event type, used to find changes to the entity
+/// hierarchy once per frame for all of them.
+#[derive(Resource, Default)]
+pub(crate) struct ListenerGraphInvalidation {
+    /// Discards the parts of an [`EventDispatcher`]'s cached graph affected by changes to
+    /// [`Parent`], one for each event type.
+    invalidators: Vec<fn(&mut World, &[Entity])>,
+    /// Set when any event type has events to dispatch this frame.
+    requested: bool,
+}
+
+impl ListenerGraphInvalidation {
+    /// Register the [`EventDispatcher<E>`] to be invalidated when the hierarchy changes.
+    pub(crate) fn register<E: EntityEvent>(&mut self) {
+        self.invalidators.push(|world, changed| {
+            world.resource_scope(|world, mut dispatcher: Mut<EventDispatcher<E>>| {
+                if dispatcher.is_cache_empty() {
+                    return;
+                }
+                dispatcher.invalidate(changed, false, |entity| {
+                    world
+                        .get::<Children>(entity)
+                        .map(|c| &**c)
+                        .unwrap_or_default()
+                });
+            });
+        });
+    }
 
-    if let Some(first_listener) = first_listener {
-        // Only add events if they interact with an event listener.
-        dispatcher.events.push((event.to_owned(), first_listener));
-        target_cache.insert(event.target(), first_listener);
+    /// Request that [`invalidate_listener_graphs`] runs this frame. This runs for each event type
+    /// when there are events to dispatch.
+    pub(crate) fn request(mut invalidation: ResMut<Self>) {
+        invalidation.requested = true;
+    }
+
+    /// Has invalidation been requested this frame?
+    pub(crate) fn is_requested(invalidation: Res<Self>) -> bool {
+        invalidation.requested
+    }
+}
+
+/// Finds entities with a [`Parent`] that was added or changed, and discards the parts of every
+/// event type's cached listener graph that pass through them.
+///
+/// Finding changes requires checking every entity with a [`Parent`], so this only runs on frames
+/// where some event type has events to dispatch, and only once for all event types. Change
+/// detection finds every change since it last ran, so no changes are missed on other frames.
+/// Changes to [`Children`] are always paired with a change to the [`Parent`] of the child, so they
+/// do not need to be tracked separately.
+pub(crate) fn invalidate_listener_graphs(
+    world: &mut World,
+    changed_parents: &mut SystemState<Query<Entity, Changed<Parent>>>,
+) {
+    #[cfg(feature = "trace")]
+    let _span = info_span!("invalidate_listener_graphs").entered();
+
+    world.resource_mut::<ListenerGraphInvalidation>().requested = false;
+    let changed: Vec<Entity> = changed_parents.get(world).iter().collect();
+    if changed.is_empty() {
+        return;
+    }
+    let invalidators = world
+        .resource::<ListenerGraphInvalidation>()
+        .invalidators
+        .clone();
+    for invalidate in invalidators {
+        invalidate(world, &changed);
+    }
+}
+
+/// Walks up the entity hierarchy starting at `target`, visiting each entity an event would bubble
+/// through. The `visit` closure returns the next entity to visit, usually the parent of the current
+/// entity, or `None` to stop traversal. If the event cannot bubble, only the target is visited.
+pub(crate) fn walk_branch(
+    target: Entity,
+    can_bubble: bool,
+    mut visit: impl FnMut(Entity) -> Option<Entity>,
+) {
+    let mut this_node = target;
+    while let Some(next_node) = visit(this_node) {
+        if !can_bubble {
+            break;
+        }
+        this_node = next_node;
     }
 }
 
@@ -198,6 +1564,10 @@ impl<E: EntityEvent> Default for EventDispatcher<E> {
         Self {
             events: Vec::new(),
             listener_graph: HashMap::new(),
+            target_cache: HashMap::new(),
+            removed: Vec::new(),
+            stats: None,
+            generalizations: Vec::new(),
         }
     }
 }
@@ -1,10 +1,17 @@
 //! This module provides event listeners, [`On`], the most important part of
 //! [`bevy_eventlistener`](crate).
 
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 
-use crate::callbacks::{CallbackSystem, ListenerInput};
-use bevy_ecs::{prelude::*, system::EntityCommands, world::Command};
+use crate::callbacks::{Callback, CallbackSystem, ListenerCallback, ListenerInput, SharedCallback};
+use bevy_ecs::{
+    prelude::*,
+    system::{EntityCommands, ReadOnlySystem},
+    world::Command,
+};
 #[cfg(feature = "trace")]
 use bevy_utils::tracing::error;
 
@@ -17,6 +24,143 @@ pub trait EntityEvent: Event + Clone {
     fn can_bubble(&self) -> bool {
         false
     }
+    /// Every entity targeted by this event. Each target gets its own bubble up the hierarchy, as if
+    /// a copy of the event was sent to each target. By default, this is only
+    /// [`EntityEvent::target`].
+    ///
+    /// ```
+    /// # use bevy_ecs::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// #[derive(Clone, Event)]
+    /// struct Explosion {
+    ///     hit: Vec<Entity>,
+    /// }
+    ///
+    /// impl EntityEvent for Explosion {
+    ///     fn target(&self) -> Entity {
+    ///         self.hit.first().copied().unwrap_or(Entity::PLACEHOLDER)
+    ///     }
+    ///     fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+    ///         self.hit.iter().copied()
+    ///     }
+    ///     fn can_bubble(&self) -> bool {
+    ///         true
+    ///     }
+    /// }
+    /// ```
+    fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+        std::iter::once(self.target())
+    }
+    /// When several targets of this event share a listener on a common ancestor, should that
+    /// listener only be run once for the event, instead of once per target? This is disabled by
+    /// default.
+    ///
+    /// When enabled, a shared listener is only run by the bubble of the first target that reaches
+    /// it, in the order returned by [`EntityEvent::targets`]. Listeners above it are also left to
+    /// that bubble, so if it stops propagating, they are not run by the other bubbles either.
+    fn deduplicate_listeners(&self) -> bool {
+        false
+    }
+    /// Should events of this type be broadcast down the entity hierarchy to every descendant of the
+    /// target, instead of bubbling up? This returns the order descendants are visited in, and is
+    /// `None` by default.
+    ///
+    /// A broadcast event reaches the target and every descendant with an [`On`] listener, in
+    /// [`Broadcast::BreadthFirst`] or [`Broadcast::DepthFirst`] order. Listeners can call
+    /// [`ListenerInput::skip_descendants`] to prune their subtree, or
+    /// [`ListenerInput::stop_propagation`] to stop the broadcast entirely.
+    ///
+    /// ```
+    /// # use bevy_ecs::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// #[derive(Clone, Event)]
+    /// struct Paused {
+    ///     scene: Entity,
+    /// }
+    ///
+    /// impl EntityEvent for Paused {
+    ///     fn target(&self) -> Entity {
+    ///         self.scene
+    ///     }
+    ///     fn broadcast(&self) -> Option<Broadcast> {
+    ///         Some(Broadcast::BreadthFirst)
+    ///     }
+    /// }
+    /// ```
+    fn broadcast(&self) -> Option<Broadcast> {
+        None
+    }
+    /// The maximum number of levels this event can propagate away from its target, up the
+    /// hierarchy when bubbling, or down the hierarchy when [broadcast](EntityEvent::broadcast).
+    /// Listeners further away than this are not run. A maximum depth of `0` only reaches the target.
+    /// This is `None`, with no limit, by default.
+    fn max_depth(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// The order a broadcast [`EntityEvent`] visits the descendants of its target in. See
+/// [`EntityEvent::broadcast`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Broadcast {
+    /// Visit the target, then all of its children, then all of its grandchildren, and so on.
+    BreadthFirst,
+    /// Visit the target, then each child followed by all of that child's descendants, before moving
+    /// on to the next child.
+    DepthFirst,
+}
+
+/// The entity that an event forwarded with [`On::forward`] is dispatched from.
+pub enum ForwardFrom<E: EntityEvent> {
+    /// The target of the event that reached the listener.
+    Target,
+    /// The entity with the listener that forwards the event.
+    Listener,
+    /// An entity computed from the event that reached the listener, e.g. read from its data.
+    Computed(Box<ComputeEntity<E>>),
+}
+
+type ComputeEntity<E> = dyn Fn(&ListenerInput<E>) -> Entity + Send + Sync;
+
+impl<E: EntityEvent> ForwardFrom<E> {
+    /// Dispatch the forwarded event from the entity returned by `entity`.
+    pub fn computed(entity: impl 'static + Send + Sync + Fn(&ListenerInput<E>) -> Entity) -> Self {
+        Self::Computed(Box::new(entity))
+    }
+
+    /// The entity to dispatch the event forwarded from `input` from.
+    pub fn entity(&self, input: &ListenerInput<E>) -> Entity {
+        match self {
+            Self::Target => input.target(),
+            Self::Listener => input.listener(),
+            Self::Computed(entity) => entity(input),
+        }
+    }
+}
+
+/// An event listener that observes every [`EntityEvent`] type with an
+/// [`EventListenerPlugin`](crate::EventListenerPlugin), for debugging or analytics. The callback
+/// receives a type-erased [`AnyListenerInput`](crate::callbacks::AnyListenerInput) each time an
+/// event bubbles past, is broadcast to, or targets this entity.
+///
+/// The callback is run before any [`On`] listener for the same event on this entity. It can't modify
+/// the event or stop its propagation.
+#[derive(Component)]
+pub struct OnAny {
+    pub(crate) callback: Arc<Mutex<CallbackSystem>>,
+}
+
+impl OnAny {
+    /// Run a callback system every time any event reaches this listener. The callback system can
+    /// access the [`AnyListenerInput`](crate::callbacks::AnyListenerInput) resource, more easily
+    /// accessed with the [`AnyListener`](crate::callbacks::AnyListener) system param.
+    pub fn run<Marker>(callback: impl IntoSystem<(), (), Marker>) -> Self {
+        Self {
+            callback: Arc::new(Mutex::new(CallbackSystem::New(Box::new(
+                IntoSystem::into_system(callback),
+            )))),
+        }
+    }
 }
 
 /// An event listener with a callback that is triggered when an [`EntityEvent`] bubbles past or
@@ -30,10 +174,82 @@ pub trait EntityEvent: Event + Clone {
 pub struct On<E: EntityEvent> {
     phantom: PhantomData<E>,
     /// A function that is called when the event listener is triggered.
-    pub(crate) callback: CallbackSystem,
+    pub(crate) callback: Callback<E>,
+    /// The template this listener was built from, used to build a fresh copy in [`On::try_clone`].
+    template: Option<ListenerTemplate<E>>,
+}
+
+/// A cloneable factory for [`On`] listeners, which stores the constructor of the listener instead of
+/// the listener itself. Each listener it builds has its own callback system and state.
+///
+/// Use a template to give many entities the same listener. Listeners built from a template can also
+/// be copied with [`On::try_clone`]:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::prelude::*;
+/// # #[derive(Clone, Event, EntityEvent)]
+/// # struct Hit(#[target] Entity);
+/// let template = ListenerTemplate::new(|| {
+///     On::<Hit>::run(|event: Listener<Hit>, mut hits: Local<u32>| {
+///         *hits += 1;
+///         info!("{:?} was hit {} times", event.listener(), *hits);
+///     })
+/// });
+///
+/// let mut world = World::new();
+/// world.spawn_batch((0..10).map(|_| template.build()));
+///
+/// let on_hit = template.build();
+/// let copy = on_hit.try_clone().unwrap();
+/// world.spawn_batch([on_hit, copy]);
+/// ```
+pub struct ListenerTemplate<E: EntityEvent> {
+    build: Arc<ListenerConstructor<E>>,
+}
+
+type ListenerConstructor<E> = dyn Fn() -> On<E> + Send + Sync;
+
+impl<E: EntityEvent> Clone for ListenerTemplate<E> {
+    fn clone(&self) -> Self {
+        Self {
+            build: self.build.clone(),
+        }
+    }
+}
+
+impl<E: EntityEvent> ListenerTemplate<E> {
+    /// Create a template that builds listeners with `build`, e.g. `|| On::run(my_callback)`.
+    pub fn new(build: impl 'static + Send + Sync + Fn() -> On<E>) -> Self {
+        Self {
+            build: Arc::new(build),
+        }
+    }
+
+    /// Build a new listener from this template, which can itself be copied with
+    /// [`On::try_clone`].
+    pub fn build(&self) -> On<E> {
+        On {
+            template: Some(self.clone()),
+            ..(self.build)()
+        }
+    }
+}
+
+impl<E: EntityEvent> From<ListenerTemplate<E>> for On<E> {
+    fn from(template: ListenerTemplate<E>) -> Self {
+        template.build()
+    }
 }
 
 impl<E: EntityEvent> On<E> {
+    /// Build a fresh copy of this listener, with its own callback state, from the
+    /// [`ListenerTemplate`] it was built from. Returns `None` if the listener wasn't built from a
+    /// template, because its callback system can't be cloned.
+    pub fn try_clone(&self) -> Option<Self> {
+        self.template.as_ref().map(ListenerTemplate::build)
+    }
+
     /// Run a callback system every time this event listener is triggered. This can be a closure or
     /// a function, as described by bevy's documentation. The only notable difference from Bevy
     /// systems is that the callback system can access a resource with event data,
@@ -42,7 +258,116 @@ impl<E: EntityEvent> On<E> {
     pub fn run<Marker>(callback: impl IntoSystem<(), (), Marker>) -> Self {
         Self {
             phantom: PhantomData,
-            callback: CallbackSystem::New(Box::new(IntoSystem::into_system(callback))),
+            template: None,
+            callback: Callback::new(ListenerCallback::Exclusive(CallbackSystem::New(Box::new(
+                IntoSystem::into_system(callback),
+            )))),
+        }
+    }
+
+    /// Run a [`SharedCallback`] every time this event listener is triggered. Like [`On::run`], the
+    /// system can access the [`ListenerInput`].
+    ///
+    /// Every listener that runs the same shared callback shares a single instance of its system,
+    /// instead of each listener allocating and initializing its own, which saves memory and
+    /// initialization time when many entities have the same callback, like every button in a UI.
+    /// The system also shares its state, e.g. in [`Local`]s, between these listeners. Cloning the
+    /// listener creates another listener for the same shared callback.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// # #[derive(Clone, Event, EntityEvent)]
+    /// # struct Clicked(#[target] Entity);
+    /// fn handle_click(event: Listener<Clicked>) {
+    ///     info!("{:?} was clicked", event.listener());
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// let handle_click = SharedCallback::new(handle_click);
+    /// for _ in 0..1000 {
+    ///     world.spawn(On::<Clicked>::run_shared(&handle_click));
+    /// }
+    /// ```
+    pub fn run_shared(callback: &SharedCallback) -> Self {
+        let callback = callback.clone();
+        ListenerTemplate::new(move || Self {
+            phantom: PhantomData,
+            template: None,
+            callback: Callback::new(ListenerCallback::Exclusive(CallbackSystem::Shared(
+                callback.clone(),
+            ))),
+        })
+        .build()
+    }
+
+    /// Run a callback system once per frame with every event that reached this listener, instead of
+    /// once per event. The callback system can access a
+    /// [`ListenerBatch`](crate::callbacks::ListenerBatch) resource in place of [`ListenerInput`],
+    /// more easily accessed with the system params [`BatchListener`](crate::callbacks::BatchListener)
+    /// and [`BatchListenerMut`](crate::callbacks::BatchListenerMut).
+    ///
+    /// This avoids the overhead of running the callback for each event when many events reach the
+    /// same listener. Events wait at a batch listener until every event that will reach it this
+    /// frame has arrived, so listeners further up the hierarchy see these events after events that
+    /// did not pass through a batch listener. Each event in the batch can stop its own propagation.
+    pub fn run_batch<Marker>(callback: impl IntoSystem<(), (), Marker>) -> Self {
+        Self {
+            phantom: PhantomData,
+            template: None,
+            callback: Callback::new(ListenerCallback::Batch(CallbackSystem::New(Box::new(
+                IntoSystem::into_system(callback),
+            )))),
+        }
+    }
+
+    /// Run a read-only callback system every time this event listener is triggered. The callback
+    /// receives the [`ListenerInput`] as system input with [`In`], and can only read from the world,
+    /// though it can still use [`Commands`], e.g. to send events.
+    ///
+    /// Read-only callbacks for different events are run concurrently on the
+    /// [`ComputeTaskPool`](bevy_tasks::ComputeTaskPool), until the next callback that needs
+    /// exclusive access to the world. Callbacks for a single event still run one after another, in
+    /// the order the event reaches them, and commands are applied in that order before any later
+    /// callbacks run. Because they can't modify the event, read-only callbacks can't stop
+    /// propagation.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// # #[derive(Clone, Event, EntityEvent)]
+    /// # struct Attack {
+    /// #     #[target]
+    /// #     target: Entity,
+    /// # }
+    /// # #[derive(Component)]
+    /// # struct Armor(u32);
+    /// On::<Attack>::run_read_only(|In(event): In<ListenerInput<Attack>>, armor: Query<&Armor>| {
+    ///     if let Ok(armor) = armor.get(event.listener()) {
+    ///         info!("{:?} has {} armor", event.listener(), armor.0);
+    ///     }
+    /// });
+    /// ```
+    pub fn run_read_only<Marker, S>(callback: S) -> Self
+    where
+        S: IntoSystem<ListenerInput<E>, (), Marker>,
+        S::System: ReadOnlySystem,
+    {
+        Self {
+            phantom: PhantomData,
+            template: None,
+            callback: Callback::new(ListenerCallback::read_only(IntoSystem::into_system(
+                callback,
+            ))),
+        }
+    }
+
+    /// Only run the callback for events that `filter` accepts. Other events pass through this
+    /// listener as if it had no callback, so they are not counted as reaching it.
+    pub(crate) fn filter(self, filter: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            callback: self.callback.with_filter(filter),
+            ..self
         }
     }
 
@@ -178,10 +503,45 @@ impl<E: EntityEvent> On<E> {
         )
     }
 
-    /// Take the boxed system callback out of this listener, leaving an empty one behind.
-    pub(crate) fn take(&mut self) -> CallbackSystem {
-        let mut temp = CallbackSystem::Empty;
-        std::mem::swap(&mut self.callback, &mut temp);
-        temp
+    /// Forward this event as another [`EntityEvent`] `F` any time this event listener is triggered,
+    /// dispatched from the entity chosen by `from`. The `convert` function creates the forwarded
+    /// event from this event and the chosen entity, which it should use as the target of `F`. Like
+    /// other events sent by callbacks, the forwarded event is dispatched on the next frame, and this
+    /// event keeps propagating.
+    ///
+    /// This can be used to declare adapters between input-level and gameplay-level events on
+    /// entities:
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// #[derive(Clone, Event, EntityEvent)]
+    /// #[can_bubble]
+    /// struct Clicked(#[target] Entity);
+    ///
+    /// #[derive(Clone, Event, EntityEvent)]
+    /// #[can_bubble]
+    /// struct Activated {
+    ///     #[target]
+    ///     button: Entity,
+    /// }
+    ///
+    /// fn spawn_button(mut commands: Commands) {
+    ///     // Clicking any part of the button activates the button itself.
+    ///     commands.spawn(On::<Clicked>::forward(ForwardFrom::Listener, |_, button| {
+    ///         Activated { button }
+    ///     }));
+    /// }
+    /// ```
+    pub fn forward<F: EntityEvent>(
+        from: ForwardFrom<E>,
+        mut convert: impl 'static + Send + Sync + FnMut(&ListenerInput<E>, Entity) -> F,
+    ) -> Self {
+        Self::run(
+            move |event: Res<ListenerInput<E>>, mut ev: EventWriter<F>| {
+                let entity = from.entity(&event);
+                ev.send(convert(&event, entity));
+            },
+        )
     }
 }
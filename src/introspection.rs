@@ -0,0 +1,310 @@
+//! Tools for inspecting event listeners while debugging.
+//!
+//! - [`ListenerPaths`] finds which event listeners an [`EntityEvent`] would visit without sending
+//!   it.
+//! - [`ListenerGraphSnapshot`] captures the listener graph cached by the [`EventDispatcher`], and
+//!   can export it as Graphviz DOT text.
+
+use std::{collections::VecDeque, fmt::Write, marker::PhantomData};
+
+use bevy_core::Name;
+use bevy_ecs::{prelude::*, system::SystemParam};
+use bevy_hierarchy::{Children, Parent};
+use bevy_utils::HashMap;
+
+use crate::{
+    event_dispatcher::{walk_branch, EventDispatcher},
+    event_listener::{Broadcast, On},
+    EntityEvent,
+};
+
+/// A [`SystemParam`] that answers "what listens to `E` on this entity or its ancestors?".
+///
+/// The path is found by walking the entity hierarchy in the same way the [`EventDispatcher`] does
+/// when it builds the listener graph, so the result matches the order the [`On<E>`] callbacks would
+/// run in if an event was sent right now. Only [`On<E>`] listeners are included: [`OnAny`]
+/// listeners and listeners for the general event types added with
+/// [`EventListenerPlugin::dispatch_as`](crate::EventListenerPlugin::dispatch_as) are not. For events
+/// with several [`EntityEvent::targets`], only the path from [`EntityEvent::target`] is found.
+///
+/// [`OnAny`]: crate::event_listener::OnAny
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::{introspection::ListenerPaths, prelude::*};
+/// # #[derive(Clone, Event, EntityEvent)]
+/// # #[can_bubble]
+/// # struct Attack {
+/// #     #[target]
+/// #     target: Entity,
+/// # }
+/// fn debug_listeners(paths: ListenerPaths<Attack>, armor: Query<Entity, With<Name>>) {
+///     for entity in &armor {
+///         for node in paths.bubble_path(entity) {
+///             info!("{entity:?} -> {:?} at depth {}", node.listener, node.depth);
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct ListenerPaths<'w, 's, E: EntityEvent> {
+    listeners: Query<'w, 's, (Option<&'static On<E>>, Option<&'static Parent>)>,
+    children: Query<'w, 's, &'static Children>,
+}
+
+/// An entity with an [`On<E>`] listener found by [`ListenerPaths`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListenerPathNode {
+    /// The entity with the event listener.
+    pub listener: Entity,
+    /// How many levels up the hierarchy this listener is from the target, or down the hierarchy for
+    /// broadcast events. A listener on the target itself has a depth of `0`.
+    pub depth: usize,
+    /// Does the listener have no callback, like a default [`On<E>`]? Callbacks that have been moved
+    /// into the dispatcher's listener graph, or are currently being run, are not missing.
+    pub is_empty: bool,
+}
+
+impl<'w, 's, E: EntityEvent> ListenerPaths<'w, 's, E> {
+    /// The listeners that `event` would visit, in the order they would be run. Broadcast events
+    /// visit the target's descendants, in the order given by [`EntityEvent::broadcast`].
+    pub fn path(&self, event: &E) -> Vec<ListenerPathNode> {
+        let mut path = match event.broadcast() {
+            Some(broadcast) => {
+                let max_depth = event.max_depth().unwrap_or(usize::MAX);
+                self.broadcast_path(event.target(), broadcast, max_depth)
+            }
+            None => self.path_from(event.target(), event.can_bubble()),
+        };
+        if let Some(max_depth) = event.max_depth() {
+            path.retain(|node| node.depth <= max_depth);
+        }
+        // Listeners that only run their callback for some events are skipped by the others.
+        path.retain(|node| match self.listeners.get(node.listener) {
+            Ok((Some(listener), _)) => listener.callback.accepts(event),
+            _ => true,
+        });
+        path
+    }
+
+    /// The listeners an event targeting `target` would visit if it bubbles.
+    pub fn bubble_path(&self, target: Entity) -> Vec<ListenerPathNode> {
+        self.path_from(target, true)
+    }
+
+    /// The listeners an event targeting `target` would visit. If `can_bubble` is `false`, this only
+    /// includes the listener on the target itself, if any.
+    pub fn path_from(&self, target: Entity, can_bubble: bool) -> Vec<ListenerPathNode> {
+        let mut path = Vec::new();
+        let mut depth = 0;
+        walk_branch(target, can_bubble, |entity| {
+            let (listener, parent) = self.listeners.get(entity).ok()?;
+            if let Some(listener) = listener {
+                path.push(ListenerPathNode {
+                    listener: entity,
+                    depth,
+                    is_empty: listener.callback.is_empty(),
+                });
+            }
+            depth += 1;
+            parent.map(Parent::get)
+        });
+        path
+    }
+
+    /// The listeners an event broadcast from `target` would visit, in `broadcast` order, down to
+    /// `max_depth` levels below the target. This doesn't know which listeners would call
+    /// [`ListenerInput::skip_descendants`](crate::callbacks::ListenerInput::skip_descendants).
+    pub fn broadcast_path(
+        &self,
+        target: Entity,
+        broadcast: Broadcast,
+        max_depth: usize,
+    ) -> Vec<ListenerPathNode> {
+        let mut path = Vec::new();
+        let mut pending = VecDeque::from([(target, 0)]);
+        while let Some((entity, depth)) = match broadcast {
+            Broadcast::BreadthFirst => pending.pop_front(),
+            Broadcast::DepthFirst => pending.pop_back(),
+        } {
+            if let Ok((Some(listener), _)) = self.listeners.get(entity) {
+                path.push(ListenerPathNode {
+                    listener: entity,
+                    depth,
+                    is_empty: listener.callback.is_empty(),
+                });
+            }
+            let children = self.children.get(entity).ok();
+            if let Some(children) = children.filter(|_| depth < max_depth) {
+                let children = children.iter().map(|&child| (child, depth + 1));
+                match broadcast {
+                    Broadcast::BreadthFirst => pending.extend(children),
+                    Broadcast::DepthFirst => pending.extend(children.rev()),
+                }
+            }
+        }
+        path
+    }
+
+    /// Does an event targeting `target` reach any listener if it bubbles?
+    pub fn has_listeners(&self, target: Entity) -> bool {
+        !self.bubble_path(target).is_empty()
+    }
+}
+
+/// A copy of the listener graph cached by the [`EventDispatcher<E>`].
+///
+/// The dispatcher keeps its graph across frames, and only discards the branches affected by changes
+/// to the hierarchy or listeners. The snapshot holds every listener in this cached graph, including
+/// listeners only reached by events on earlier frames, while [`Self::targets`] only holds the
+/// targets of events sent this frame. Initializing this resource enables capturing the graph so it
+/// can be inspected later, for example by writing it to a file with [`Self::to_dot`]:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_eventlistener::{introspection::ListenerGraphSnapshot, prelude::*};
+/// # #[derive(Clone, Event, EntityEvent)]
+/// # struct Attack {
+/// #     #[target]
+/// #     target: Entity,
+/// # }
+/// App::new()
+///     .add_plugins(EventListenerPlugin::<Attack>::default())
+///     .init_resource::<ListenerGraphSnapshot<Attack>>()
+///     .add_systems(Update, |snapshot: Res<ListenerGraphSnapshot<Attack>>| {
+///         if snapshot.is_changed() {
+///             std::fs::write("attack.dot", snapshot.to_dot()).ok();
+///         }
+///     });
+/// ```
+///
+/// The snapshot is only updated on frames where events of type `E` were sent.
+#[derive(Resource)]
+pub struct ListenerGraphSnapshot<E: EntityEvent> {
+    /// Every listener in the cached graph.
+    pub listeners: Vec<SnapshotListener>,
+    /// Every target of an event sent this frame that reached a listener.
+    pub targets: Vec<SnapshotTarget>,
+    phantom: PhantomData<E>,
+}
+
+/// A listener in a [`ListenerGraphSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotListener {
+    /// The entity with the [`On<E>`] listener.
+    pub entity: Entity,
+    /// The entity's [`Name`], if it has one.
+    pub name: Option<String>,
+    /// The next listener an event will bubble to after this one, if any.
+    pub next: Option<Entity>,
+}
+
+/// The target of one or more events in a [`ListenerGraphSnapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotTarget {
+    /// The entity targeted by the events.
+    pub entity: Entity,
+    /// The entity's [`Name`], if it has one.
+    pub name: Option<String>,
+    /// The first listener the events visited.
+    pub first_listener: Entity,
+    /// The number of events that targeted this entity.
+    pub events: usize,
+}
+
+impl<E: EntityEvent> Default for ListenerGraphSnapshot<E> {
+    fn default() -> Self {
+        Self {
+            listeners: Vec::new(),
+            targets: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: EntityEvent> ListenerGraphSnapshot<E> {
+    /// Copy the listener graph out of the dispatcher. This runs between building the graph and
+    /// bubbling events when this resource exists.
+    pub(crate) fn capture(
+        mut snapshot: ResMut<Self>,
+        dispatcher: Res<EventDispatcher<E>>,
+        names: Query<&Name>,
+    ) {
+        let name = |entity| names.get(entity).ok().map(|name| name.as_str().to_owned());
+
+        let mut listeners: Vec<_> = dispatcher
+            .listener_graph
+            .iter()
+            .map(|(&entity, node)| SnapshotListener {
+                entity,
+                name: name(entity),
+                next: node.next,
+            })
+            .collect();
+        listeners.sort_by_key(|listener| listener.entity);
+
+        let mut targets = HashMap::<(Entity, Entity), usize>::new();
+        for pending in &dispatcher.events {
+            *targets
+                .entry((pending.target, pending.first_listener))
+                .or_default() += 1;
+        }
+        let mut targets: Vec<_> = targets
+            .into_iter()
+            .map(|((entity, first_listener), events)| SnapshotTarget {
+                entity,
+                name: name(entity),
+                first_listener,
+                events,
+            })
+            .collect();
+        targets.sort_by_key(|target| target.entity);
+
+        snapshot.listeners = listeners;
+        snapshot.targets = targets;
+    }
+
+    /// Write the snapshot as a Graphviz DOT digraph. Listeners are drawn as boxes connected in the
+    /// order events bubble through them, and event targets are drawn as ellipses with a dashed edge
+    /// to the first listener their events visited.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let label = |entity: Entity, name: &Option<String>| match name {
+            Some(name) => escape(&format!("{name} ({entity})")),
+            None => entity.to_string(),
+        };
+
+        writeln!(dot, "digraph \"{}\" {{", escape(std::any::type_name::<E>())).unwrap();
+        writeln!(dot, "    node [shape=box];").unwrap();
+        for listener in &self.listeners {
+            let entity = listener.entity;
+            let label = label(entity, &listener.name);
+            writeln!(dot, "    \"{entity}\" [label=\"{label}\"];").unwrap();
+            if let Some(next) = listener.next {
+                writeln!(dot, "    \"{entity}\" -> \"{next}\";").unwrap();
+            }
+        }
+        for target in &self.targets {
+            let (entity, first_listener) = (target.entity, target.first_listener);
+            let label = label(entity, &target.name);
+            let events = target.events;
+            writeln!(
+                dot,
+                "    \"target {entity}\" [label=\"{label}\", shape=ellipse, style=dashed];"
+            )
+            .unwrap();
+            writeln!(
+                dot,
+                "    \"target {entity}\" -> \"{first_listener}\" [style=dashed, label=\"{events}\"];"
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Escape a string so it can be used inside a quoted DOT identifier.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -68,6 +68,7 @@
 //! - Depth: 64 (how many levels of children for an entity at the root)
 //! - Total nodes: 12,800 (total number of entities spawned)
 //! - Listener density: 20% (what percent of entities have event listeners?)
+//!
 //! ![image](https://github.com/aevyrie/bevy_eventlistener/assets/2632925/72f75640-8b44-4ace-af67-9898c4c78321)
 //!
 //! The blue line can be read as "how long does it take all of these events to bubble up a hierarchy
@@ -83,10 +84,19 @@
 pub use bevy_eventlistener_derive::EntityEvent;
 pub use plugin::*;
 
+// Lets the `EntityEvent` derive refer to this crate by name from inside it.
+extern crate self as bevy_eventlistener;
+
 /// Common exports
 pub mod prelude {
-    pub use crate::callbacks::{Listener, ListenerInput, ListenerMut};
-    pub use crate::event_listener::{EntityEvent, On};
+    pub use crate::callbacks::{
+        AnyListener, AnyListenerInput, BatchListener, BatchListenerMut, Listener, ListenerBatch,
+        ListenerInput, ListenerMut, SharedCallback,
+    };
+    pub use crate::delayed::{Delay, DelayedEventCommands, DelayedEventHandle};
+    pub use crate::event_listener::{
+        Broadcast, EntityEvent, ForwardFrom, ListenerTemplate, On, OnAny,
+    };
     pub use crate::EventListenerPlugin;
     pub use bevy_eventlistener_derive::EntityEvent;
 }
@@ -94,9 +104,17 @@ pub mod prelude {
 use event_listener::EntityEvent;
 
 pub mod callbacks;
+pub mod delayed;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod dynamic;
 pub mod event_dispatcher;
 pub mod event_listener;
+pub mod introspection;
 pub mod plugin;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod testing;
 
 #[test]
 fn replace_listener() {
@@ -169,3 +187,1716 @@ fn replace_listener_in_callback() {
     assert_eq!(receiver.recv(), Ok("one"));
     assert_eq!(receiver.recv(), Ok("two"));
 }
+
+#[test]
+fn listener_path() {
+    use crate::{introspection::ListenerPaths, prelude::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(On::<Foo>::run(|| {})).id();
+    let middle = app.world_mut().spawn_empty().set_parent(root).id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<Foo>::run(
+            move |event: Listener<Foo>, paths: ListenerPaths<Foo>| {
+                let path = paths.path(&event);
+                let nodes: Vec<_> = path
+                    .iter()
+                    .map(|node| (node.listener, node.depth, node.is_empty))
+                    .collect();
+                tx.send(nodes).unwrap();
+            },
+        ))
+        .set_parent(middle)
+        .id();
+
+    app.world_mut().send_event(Foo { target: leaf });
+    app.update();
+
+    // Listeners aren't empty while their callbacks are in the listener graph or being run.
+    assert_eq!(rx.recv(), Ok(vec![(leaf, 0, false), (root, 2, false)]));
+
+    let mut paths = bevy::ecs::system::SystemState::<ListenerPaths<Foo>>::new(app.world_mut());
+    let paths = paths.get(app.world());
+    assert!(!paths.bubble_path(leaf)[1].is_empty);
+    assert_eq!(paths.path(&Foo { target: middle }).len(), 1);
+    assert!(paths.path_from(middle, false).is_empty());
+    assert!(paths.has_listeners(middle));
+
+    // `On<Foo>` is only `Default` if `Foo` is.
+    impl Default for Foo {
+        fn default() -> Self {
+            Self {
+                target: Entity::PLACEHOLDER,
+            }
+        }
+    }
+    app.world_mut()
+        .entity_mut(middle)
+        .insert(On::<Foo>::default());
+    let mut paths = bevy::ecs::system::SystemState::<ListenerPaths<Foo>>::new(app.world_mut());
+    let paths = paths.get(app.world());
+    let path: Vec<_> = paths
+        .bubble_path(leaf)
+        .iter()
+        .map(|node| (node.listener, node.is_empty))
+        .collect();
+    assert_eq!(path, [(leaf, false), (middle, true), (root, false)]);
+}
+
+#[test]
+fn dispatch_stats() {
+    use crate::{event_dispatcher::EventDispatcher, prelude::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(On::<Foo>::run(|| {})).id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<Foo>::run(|| {}))
+        .set_parent(root)
+        .id();
+    let dead = app.world_mut().spawn_empty().id();
+
+    let mut dispatcher = app.world_mut().resource_mut::<EventDispatcher<Foo>>();
+    assert!(dispatcher.stats().is_none());
+    dispatcher.enable_stats();
+
+    app.world_mut().send_event(Foo { target: leaf });
+    app.world_mut().send_event(Foo { target: root });
+    app.world_mut().send_event(Foo { target: dead });
+    app.world_mut().send_event(Foo { target: dead });
+    app.update();
+
+    let mut dispatcher = app.world_mut().resource_mut::<EventDispatcher<Foo>>();
+    let stats = dispatcher.take_stats().unwrap();
+    assert_eq!(stats.events_received, 4);
+    assert_eq!(stats.events_dead_branch, 2);
+    assert_eq!(stats.listeners_invoked, 3);
+    assert_eq!(stats.graph_nodes_built, 2);
+    assert_eq!(dispatcher.stats().unwrap().events_received, 0);
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn dispatch_diagnostics() {
+    use crate::{diagnostics::*, prelude::*};
+    use bevy::{diagnostic::DiagnosticsStore, prelude::*};
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default())
+        .add_plugins(EventListenerDiagnosticsPlugin::<Foo>::default());
+    let entity = app.world_mut().spawn(On::<Foo>::run(|| {})).id();
+    app.update();
+
+    app.world_mut().send_event(Foo { target: entity });
+    app.update();
+
+    let paths = app.world().resource::<DispatchDiagnosticPaths<Foo>>();
+    let store = app.world().resource::<DiagnosticsStore>();
+    let invoked = store.get_measurement(&paths.listeners_invoked).unwrap();
+    assert_eq!(invoked.value, 1.0);
+}
+
+#[test]
+fn listener_graph_dot() {
+    use crate::{introspection::ListenerGraphSnapshot, prelude::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default())
+        .init_resource::<ListenerGraphSnapshot<Foo>>();
+    let root = app
+        .world_mut()
+        .spawn((Name::new("Root \"A\""), On::<Foo>::run(|| {})))
+        .id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<Foo>::run(|| {}))
+        .set_parent(root)
+        .id();
+
+    app.world_mut().send_event(Foo { target: leaf });
+    app.world_mut().send_event(Foo { target: leaf });
+    app.update();
+
+    let snapshot = app.world().resource::<ListenerGraphSnapshot<Foo>>();
+    assert_eq!(snapshot.listeners.len(), 2);
+    assert_eq!(snapshot.targets[0].events, 2);
+    let dot = snapshot.to_dot();
+    assert!(dot.contains(&format!("\"{root}\" [label=\"Root \\\"A\\\" ({root})\"];")));
+    assert!(dot.contains(&format!("\"{leaf}\" -> \"{root}\";")));
+    assert!(dot.contains(&format!("\"target {leaf}\" -> \"{leaf}\"")));
+}
+
+#[cfg(feature = "replay")]
+#[test]
+fn record_and_replay() {
+    use crate::{prelude::*, replay::*};
+    use bevy::ecs::entity::{EntityMapper, MapEntities};
+    use bevy::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Event, EntityEvent, Serialize, Deserialize)]
+    struct Foo {
+        #[target]
+        target: Entity,
+        value: u32,
+    }
+
+    impl MapEntities for Foo {
+        fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+            self.target = entity_mapper.map_entity(self.target);
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(EventListenerPlugin::<Foo>::default())
+            .add_plugins(EventReplayPlugin::<Foo>::default());
+        app
+    }
+
+    // Records the events that reach it, and sends a second event from its callback.
+    fn listener(tx: std::sync::mpsc::Sender<(Entity, u32)>) -> On<Foo> {
+        On::<Foo>::run(move |event: Listener<Foo>, mut events: EventWriter<Foo>| {
+            tx.send((event.target(), event.value)).unwrap();
+            if event.value == 1 {
+                events.send(Foo {
+                    target: event.target(),
+                    value: 2,
+                });
+            }
+        })
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut recording_app = app();
+    let entity = recording_app
+        .world_mut()
+        .spawn((ReplayId(7), listener(tx)))
+        .id();
+    recording_app.init_resource::<EventRecorder<Foo>>();
+    recording_app.update();
+    recording_app.world_mut().send_event(Foo {
+        target: entity,
+        value: 1,
+    });
+    recording_app.update();
+    assert_eq!(rx.try_recv(), Ok((entity, 1)));
+    recording_app.update();
+    assert_eq!(rx.try_recv(), Ok((entity, 2)));
+    let recording = recording_app
+        .world_mut()
+        .remove_resource::<EventRecorder<Foo>>()
+        .unwrap()
+        .into_recording();
+    let recording = EventRecording::<Foo>::from_ron(&recording.to_ron().unwrap()).unwrap();
+    // The event sent by the callback is sent again when the first event is replayed.
+    assert_eq!(recording.events.len(), 1);
+    assert_eq!(recording.events[0].frame, 1);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut replay_app = app();
+    replay_app.world_mut().spawn_batch((0..5).map(|_| ()));
+    let replayed = replay_app
+        .world_mut()
+        .spawn((ReplayId(7), listener(tx)))
+        .id();
+    assert_ne!(entity, replayed);
+    replay_app.insert_resource(EventReplayer::new(recording));
+    replay_app.update();
+    assert!(rx.try_recv().is_err());
+    replay_app.update();
+    assert_eq!(rx.try_recv(), Ok((replayed, 1)));
+    replay_app.update();
+    assert_eq!(rx.try_recv(), Ok((replayed, 2)));
+    replay_app.update();
+    assert!(rx.try_recv().is_err());
+    assert!(replay_app
+        .world()
+        .resource::<EventReplayer<Foo>>()
+        .is_finished());
+}
+
+#[test]
+fn bubbling_order() {
+    use crate::{prelude::*, testing::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let log = DispatchLog::<Foo>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(log.listener()).id();
+    let stop = app
+        .world_mut()
+        .spawn(log.stopping_listener())
+        .set_parent(root)
+        .id();
+    let middle = app.world_mut().spawn(log.listener()).set_parent(root).id();
+    let leaf = app.world_mut().spawn_empty().set_parent(middle).id();
+    let stop_leaf = app.world_mut().spawn(log.listener()).set_parent(stop).id();
+
+    app.world_mut().send_event(Foo { target: leaf });
+    app.world_mut().send_event(Foo { target: middle });
+    app.world_mut().send_event(Foo { target: stop_leaf });
+    app.update();
+
+    log.assert_dispatched_in_order_with_targets(&[
+        (middle, leaf),
+        (root, leaf),
+        (middle, middle),
+        (root, middle),
+        (stop_leaf, stop_leaf),
+        (stop, stop_leaf),
+    ]);
+    let phases: Vec<_> = log.take().iter().map(|record| record.phase).collect();
+    assert_eq!(phases[2], Phase::AtTarget);
+    assert_eq!(phases[3], Phase::Bubbling);
+    app.update();
+    log.assert_not_dispatched();
+}
+
+#[test]
+fn join_existing_branch() {
+    use crate::{prelude::*, testing::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let log = DispatchLog::<Foo>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let parent = app.world_mut().spawn(log.listener()).id();
+    let a = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(parent)
+        .id();
+    let b = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(parent)
+        .id();
+
+    // The branch from `b` joins the graph built for `a` at `parent`.
+    app.world_mut().send_event(Foo { target: a });
+    app.world_mut().send_event(Foo { target: b });
+    app.update();
+    log.assert_dispatched_in_order(&[a, parent, b, parent]);
+}
+
+#[test]
+fn cached_graph_invalidation() {
+    use crate::{prelude::*, testing::*};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let log = DispatchLog::<Foo>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(log.listener()).id();
+    let other_root = app.world_mut().spawn(log.listener()).id();
+    let middle = app.world_mut().spawn_empty().set_parent(root).id();
+    let leaf = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(middle)
+        .id();
+
+    let send = |app: &mut App| {
+        app.world_mut().send_event(Foo { target: leaf });
+        app.update();
+        log.take()
+            .iter()
+            .map(|record| record.listener)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(send(&mut app), vec![leaf, root]);
+    app.world_mut().entity_mut(middle).insert(log.listener());
+    assert_eq!(send(&mut app), vec![leaf, middle, root]);
+    app.world_mut().entity_mut(middle).remove::<On<Foo>>();
+    assert_eq!(send(&mut app), vec![leaf, root]);
+    app.world_mut().entity_mut(middle).set_parent(other_root);
+    assert_eq!(send(&mut app), vec![leaf, other_root]);
+    app.world_mut().entity_mut(other_root).despawn();
+    assert_eq!(send(&mut app), vec![leaf]);
+    app.world_mut().entity_mut(leaf).remove::<On<Foo>>();
+    assert_eq!(send(&mut app), vec![]);
+}
+
+#[test]
+fn dispatch_does_not_change_listeners() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    #[derive(Resource, Default)]
+    struct ChangedListeners(usize);
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default())
+        .init_resource::<ChangedListeners>()
+        .add_systems(
+            Last,
+            |changed: Query<(), Changed<On<Foo>>>, mut count: ResMut<ChangedListeners>| {
+                count.0 += changed.iter().count();
+            },
+        );
+    let root = app.world_mut().spawn(On::<Foo>::run(|| {})).id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<Foo>::run(|| {}))
+        .set_parent(root)
+        .id();
+
+    app.world_mut().send_event(Foo { target: leaf });
+    app.update();
+    assert_eq!(app.world().resource::<ChangedListeners>().0, 2);
+
+    // Callbacks are run in place, so dispatching events does not trigger change detection.
+    app.world_mut().send_event(Foo { target: leaf });
+    app.update();
+    assert_eq!(app.world().resource::<ChangedListeners>().0, 2);
+}
+
+#[test]
+fn batch_listener() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+        id: u32,
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let sender = tx.clone();
+    let root = app
+        .world_mut()
+        .spawn(On::<Foo>::run(move |event: Listener<Foo>| {
+            sender.send(("root", vec![event.id])).unwrap();
+        }))
+        .id();
+    let sender = tx.clone();
+    let middle = app
+        .world_mut()
+        .spawn(On::<Foo>::run_batch(
+            move |mut batch: BatchListenerMut<Foo>| {
+                sender
+                    .send(("middle", batch.iter().map(|event| event.id).collect()))
+                    .unwrap();
+                for event in &mut batch {
+                    if event.id == 2 {
+                        event.stop_propagation();
+                    }
+                }
+            },
+        ))
+        .set_parent(root)
+        .id();
+    let mut leaf = |name| {
+        let sender = tx.clone();
+        app.world_mut()
+            .spawn(On::<Foo>::run(move |event: Listener<Foo>| {
+                sender.send((name, vec![event.id])).unwrap();
+            }))
+            .set_parent(middle)
+            .id()
+    };
+    let (leaf_a, leaf_b) = (leaf("a"), leaf("b"));
+
+    app.world_mut().send_event(Foo {
+        target: leaf_a,
+        id: 1,
+    });
+    app.world_mut().send_event(Foo {
+        target: leaf_b,
+        id: 2,
+    });
+    app.world_mut().send_event(Foo {
+        target: leaf_a,
+        id: 3,
+    });
+    app.update();
+
+    let received: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            ("a", vec![1]),
+            ("b", vec![2]),
+            ("a", vec![3]),
+            ("middle", vec![1, 2, 3]),
+            ("root", vec![1]),
+            ("root", vec![3]),
+        ]
+    );
+}
+
+#[test]
+fn events_sent_by_callbacks() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Bar {
+        #[target]
+        target: Entity,
+    }
+
+    impl From<ListenerInput<Foo>> for Bar {
+        fn from(event: ListenerInput<Foo>) -> Self {
+            Bar {
+                target: event.target(),
+            }
+        }
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default())
+        .add_plugins(EventListenerPlugin::<Bar>::default());
+    let entity = app
+        .world_mut()
+        .spawn((
+            On::<Foo>::send_event::<Bar>(),
+            On::<Bar>::run(move || tx.send("bar").unwrap()),
+        ))
+        .id();
+
+    app.world_mut().send_event(Foo { target: entity });
+    app.update();
+    // Every listener graph is built before any callbacks run, so `Bar` is dispatched next frame.
+    assert!(rx.try_recv().is_err());
+    app.update();
+    assert_eq!(rx.try_recv(), Ok("bar"));
+}
+
+#[test]
+fn read_only_listener() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+        id: u32,
+    }
+
+    #[derive(Component)]
+    struct Hits(u32);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app
+        .world_mut()
+        .spawn((
+            Hits(0),
+            On::<Foo>::listener_component_mut::<Hits>(|_, hits| hits.0 += 1),
+        ))
+        .id();
+    let sender = tx.clone();
+    let middle = app
+        .world_mut()
+        .spawn(On::<Foo>::run_read_only(
+            move |In(event): In<ListenerInput<Foo>>, hits: Query<&Hits>| {
+                let hits = hits.get(root).unwrap().0;
+                sender.send(("middle", event.id, hits)).unwrap();
+            },
+        ))
+        .set_parent(root)
+        .id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<Foo>::run_read_only(
+            move |In(event): In<ListenerInput<Foo>>, mut commands: Commands| {
+                tx.send(("leaf", event.id, 0)).unwrap();
+                commands.entity(event.listener()).insert(Hits(event.id));
+            },
+        ))
+        .set_parent(middle)
+        .id();
+
+    app.world_mut().send_event(Foo {
+        target: leaf,
+        id: 1,
+    });
+    app.world_mut().send_event(Foo {
+        target: leaf,
+        id: 2,
+    });
+    app.update();
+
+    let received: Vec<_> = rx.try_iter().collect();
+    let seen_by = |name| {
+        received
+            .iter()
+            .filter(|(listener, ..)| *listener == name)
+            .map(|(_, id, hits)| (*id, *hits))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(seen_by("leaf"), [(1, 0), (2, 0)]);
+    // Read-only callbacks run before the next exclusive callback on the same path.
+    assert_eq!(seen_by("middle"), [(1, 0), (2, 1)]);
+    assert_eq!(app.world().get::<Hits>(root).unwrap().0, 2);
+    // Commands from read-only callbacks are applied.
+    assert_eq!(app.world().get::<Hits>(leaf).unwrap().0, 2);
+}
+
+#[test]
+fn read_only_listener_order() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::time::Duration;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+        id: u32,
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let listener = |name, delay| {
+        let tx = tx.clone();
+        On::<Foo>::run_read_only(move |In(event): In<ListenerInput<Foo>>| {
+            // A slow listener must still finish before the event reaches the next listener.
+            std::thread::sleep(Duration::from_millis(delay));
+            tx.send((name, event.id)).unwrap();
+        })
+    };
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(listener("root", 0)).id();
+    let middle = app
+        .world_mut()
+        .spawn(listener("middle", 10))
+        .set_parent(root)
+        .id();
+    let leaf = app
+        .world_mut()
+        .spawn(listener("leaf", 20))
+        .set_parent(middle)
+        .id();
+
+    app.world_mut().send_event(Foo {
+        target: leaf,
+        id: 1,
+    });
+    app.world_mut().send_event(Foo {
+        target: middle,
+        id: 2,
+    });
+    app.update();
+
+    let received: Vec<_> = rx.try_iter().collect();
+    let order = |id| {
+        received
+            .iter()
+            .filter(|(_, event)| *event == id)
+            .map(|(listener, _)| *listener)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(order(1), ["leaf", "middle", "root"]);
+    assert_eq!(order(2), ["middle", "root"]);
+}
+
+#[test]
+fn listener_input_path() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let listener = move || {
+        let tx = tx.clone();
+        On::<Foo>::run(move |event: Listener<Foo>| {
+            let remaining: Vec<_> = event.remaining_path().collect();
+            let path = (event.path_index(), event.previous_listener(), remaining);
+            tx.send((event.listener(), event.depth(), path)).unwrap();
+        })
+    };
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default());
+    let root = app.world_mut().spawn(listener()).id();
+    let middle = app.world_mut().spawn_empty().set_parent(root).id();
+    let parent = app.world_mut().spawn(listener()).set_parent(middle).id();
+    let target = app.world_mut().spawn_empty().set_parent(parent).id();
+
+    app.world_mut().send_event(Foo { target });
+    app.update();
+    let received: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            (parent, 1, (0, None, vec![root])),
+            (root, 3, (1, Some(parent), vec![])),
+        ]
+    );
+
+    // The cached graph gives the same result for another target in the same branch.
+    app.world_mut().send_event(Foo { target: parent });
+    app.update();
+    let received: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            (parent, 0, (0, None, vec![root])),
+            (root, 2, (1, Some(parent), vec![])),
+        ]
+    );
+}
+
+#[test]
+fn multi_target_events() {
+    use crate::{prelude::*, testing::DispatchLog};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event)]
+    struct Explosion {
+        hit: Vec<Entity>,
+        deduplicate: bool,
+    }
+
+    impl EntityEvent for Explosion {
+        fn target(&self) -> Entity {
+            self.hit[0]
+        }
+        fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+            self.hit.iter().copied()
+        }
+        fn can_bubble(&self) -> bool {
+            true
+        }
+        fn deduplicate_listeners(&self) -> bool {
+            self.deduplicate
+        }
+    }
+
+    let log = DispatchLog::<Explosion>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Explosion>::default());
+    let root = app.world_mut().spawn(log.listener()).id();
+    let a = app.world_mut().spawn(log.listener()).set_parent(root).id();
+    let b = app.world_mut().spawn(log.listener()).set_parent(root).id();
+
+    app.world_mut().send_event(Explosion {
+        hit: vec![a, b],
+        deduplicate: false,
+    });
+    app.update();
+    log.assert_dispatched_in_order_with_targets(&[(a, a), (root, a), (b, b), (root, b)]);
+    log.clear();
+
+    app.world_mut().send_event(Explosion {
+        hit: vec![a, b],
+        deduplicate: true,
+    });
+    app.update();
+    log.assert_dispatched_in_order_with_targets(&[(a, a), (root, a), (b, b)]);
+}
+
+#[test]
+fn broadcast_to_descendants() {
+    use crate::{
+        prelude::*,
+        testing::{DispatchLog, Phase},
+    };
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event)]
+    struct Paused {
+        scene: Entity,
+        order: Broadcast,
+    }
+
+    impl EntityEvent for Paused {
+        fn target(&self) -> Entity {
+            self.scene
+        }
+        fn broadcast(&self) -> Option<Broadcast> {
+            Some(self.order)
+        }
+    }
+
+    let log = DispatchLog::<Paused>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Paused>::default());
+    let parent = app.world_mut().spawn_empty().id();
+    let root = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(parent)
+        .id();
+    let a = app.world_mut().spawn(log.listener()).set_parent(root).id();
+    let a1 = app.world_mut().spawn(log.listener()).set_parent(a).id();
+    // Entities without listeners are still traversed.
+    let b = app.world_mut().spawn_empty().set_parent(root).id();
+    let b1 = app.world_mut().spawn(log.listener()).set_parent(b).id();
+    let c = app.world_mut().spawn(log.listener()).set_parent(root).id();
+    app.world_mut()
+        .entity_mut(parent)
+        .insert(log.listener())
+        .add_child(root);
+
+    let send = |app: &mut App, order| {
+        app.world_mut().send_event(Paused { scene: root, order });
+        app.update();
+    };
+
+    send(&mut app, Broadcast::BreadthFirst);
+    log.assert_dispatched_in_order(&[root, a, c, a1, b1]);
+    let records = log.take();
+    assert_eq!(records[0].phase, Phase::AtTarget);
+    assert_eq!(records[1].phase, Phase::Broadcasting);
+
+    send(&mut app, Broadcast::DepthFirst);
+    log.assert_dispatched_in_order(&[root, a, a1, b1, c]);
+    log.clear();
+
+    // `ListenerPaths` follows the broadcast down the hierarchy.
+    let mut paths =
+        bevy::ecs::system::SystemState::<crate::introspection::ListenerPaths<Paused>>::new(
+            app.world_mut(),
+        );
+    let paths = paths.get(app.world());
+    let path = |order| -> Vec<_> {
+        let event = Paused { scene: root, order };
+        let path = paths.path(&event);
+        path.iter()
+            .map(|node| (node.listener, node.depth))
+            .collect()
+    };
+    assert_eq!(
+        path(Broadcast::BreadthFirst),
+        [(root, 0), (a, 1), (c, 1), (a1, 2), (b1, 2)]
+    );
+    assert_eq!(
+        path(Broadcast::DepthFirst),
+        [(root, 0), (a, 1), (a1, 2), (b1, 2), (c, 1)]
+    );
+
+    // Pruning a subtree only skips the descendants of that listener.
+    let depths = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = depths.clone();
+    app.world_mut().entity_mut(a).insert(On::<Paused>::run(
+        move |mut event: ListenerMut<Paused>| {
+            recorded.lock().unwrap().push(event.depth());
+            event.skip_descendants();
+        },
+    ));
+    send(&mut app, Broadcast::DepthFirst);
+    log.assert_dispatched_in_order(&[root, b1, c]);
+    assert_eq!(*depths.lock().unwrap(), [1]);
+    log.clear();
+
+    // Stopping propagation ends the broadcast.
+    app.world_mut()
+        .entity_mut(b1)
+        .insert(log.stopping_listener());
+    send(&mut app, Broadcast::DepthFirst);
+    log.assert_dispatched_in_order(&[root, b1]);
+}
+
+#[test]
+fn derive_tuple_struct_and_enum() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    // The other fields are never read, they only move the target away from the first field.
+    #[allow(dead_code)]
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Clicked(Entity, #[target] Entity);
+
+    #[allow(dead_code)]
+    #[derive(Clone, Event, EntityEvent)]
+    enum Pointer {
+        Down {
+            source: Entity,
+            #[target]
+            target: Entity,
+        },
+        Up(Entity, Entity, #[target] Entity),
+    }
+
+    let [a, b, c] = [1, 2, 3].map(Entity::from_raw);
+    assert_eq!(Clicked(a, b).target(), b);
+    assert!(Clicked(a, b).can_bubble());
+    assert_eq!(
+        Pointer::Down {
+            source: a,
+            target: b
+        }
+        .target(),
+        b
+    );
+    assert_eq!(Pointer::Up(a, b, c).target(), c);
+    assert_eq!(Pointer::Up(a, b, c).targets().collect::<Vec<_>>(), [c]);
+    assert!(!Pointer::Up(a, b, c).can_bubble());
+}
+
+#[test]
+fn derive_propagation_policy() {
+    use crate::{prelude::*, testing::DispatchLog};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble = "bubbles"]
+    #[max_depth = 1]
+    struct Poke {
+        #[target]
+        target: Entity,
+        bubbles: bool,
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble(bubbles)]
+    struct Nudge {
+        #[target]
+        target: Entity,
+        bubbles: bool,
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble = "is_up()"]
+    enum Pointer {
+        Down(#[target] Entity),
+        Up(#[target] Entity),
+    }
+
+    impl Pointer {
+        fn is_up(&self) -> bool {
+            matches!(self, Pointer::Up(_))
+        }
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[broadcast(depth_first)]
+    #[max_depth = 1]
+    struct Paused(#[target] Entity);
+
+    // `#[broadcast]` doesn't need `Broadcast` in scope.
+    mod unscoped {
+        use crate::EntityEvent;
+        use bevy::prelude::{Entity, Event};
+
+        #[derive(Clone, Event, EntityEvent)]
+        #[broadcast]
+        pub struct Resumed(#[target] pub Entity);
+    }
+
+    assert!(Pointer::Up(Entity::PLACEHOLDER).can_bubble());
+    assert!(!Pointer::Down(Entity::PLACEHOLDER).can_bubble());
+    let nudge = |bubbles| Nudge {
+        target: Entity::PLACEHOLDER,
+        bubbles,
+    };
+    assert!(nudge(true).can_bubble());
+    assert!(!nudge(false).can_bubble());
+    assert_eq!(
+        Paused(Entity::PLACEHOLDER).broadcast(),
+        Some(Broadcast::DepthFirst)
+    );
+    assert_eq!(
+        unscoped::Resumed(Entity::PLACEHOLDER).broadcast(),
+        Some(Broadcast::BreadthFirst)
+    );
+
+    let pokes = DispatchLog::<Poke>::default();
+    let pauses = DispatchLog::<Paused>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Poke>::default())
+        .add_plugins(EventListenerPlugin::<Paused>::default());
+    let root = app
+        .world_mut()
+        .spawn((pokes.listener(), pauses.listener()))
+        .id();
+    let middle = app
+        .world_mut()
+        .spawn((pokes.listener(), pauses.listener()))
+        .set_parent(root)
+        .id();
+    let leaf = app
+        .world_mut()
+        .spawn((pokes.listener(), pauses.listener()))
+        .set_parent(middle)
+        .id();
+
+    app.world_mut().send_event(Poke {
+        target: leaf,
+        bubbles: true,
+    });
+    app.world_mut().send_event(Poke {
+        target: leaf,
+        bubbles: false,
+    });
+    app.world_mut().send_event(Paused(root));
+    app.update();
+    pokes.assert_dispatched_in_order(&[leaf, middle, leaf]);
+    pauses.assert_dispatched_in_order(&[root, middle]);
+}
+
+#[test]
+fn any_listener() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Event, EntityEvent, Reflect)]
+    #[can_bubble]
+    struct Foo {
+        #[target]
+        target: Entity,
+        value: u32,
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Bar {
+        #[target]
+        target: Entity,
+    }
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = seen.clone();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Foo>::default())
+        .add_plugins(EventListenerPlugin::<Bar>::default())
+        .register_type::<Foo>();
+    let root = app
+        .world_mut()
+        .spawn(OnAny::run(move |event: AnyListener| {
+            let value = event
+                .event()
+                .and_then(|event| event.downcast_ref::<Foo>())
+                .map(|foo| foo.value);
+            recorded.lock().unwrap().push((
+                event.event_type().rsplit("::").next().unwrap(),
+                event.listener(),
+                event.target(),
+                value,
+            ));
+        }))
+        .id();
+    // No `On<Foo>` or `On<Bar>` exists anywhere in the hierarchy.
+    let child = app.world_mut().spawn_empty().set_parent(root).id();
+
+    app.world_mut().send_event(Foo {
+        target: child,
+        value: 7,
+    });
+    app.world_mut().send_event(Bar { target: child });
+    app.update();
+    // Event types are dispatched in schedule order, which isn't specified.
+    let mut seen = seen.lock().unwrap().clone();
+    seen.sort();
+    assert_eq!(
+        seen,
+        [("Bar", root, child, None), ("Foo", root, child, Some(7))]
+    );
+
+    // Entities that only have an `OnAny` aren't part of the path, and aren't counted as listeners
+    // of the event.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let record = move |event: Listener<Bar>| {
+        let remaining: Vec<_> = event.remaining_path().collect();
+        let path = (event.path_index(), event.previous_listener(), remaining);
+        tx.send((event.listener(), path)).unwrap();
+    };
+    let top = app.world_mut().spawn(On::<Bar>::run(record.clone())).id();
+    let middle = app
+        .world_mut()
+        .spawn(OnAny::run(|| {}))
+        .set_parent(top)
+        .id();
+    let bottom = app
+        .world_mut()
+        .spawn(On::<Bar>::run(record))
+        .set_parent(middle)
+        .id();
+    app.world_mut()
+        .resource_mut::<crate::event_dispatcher::EventDispatcher<Bar>>()
+        .enable_stats();
+    app.world_mut().send_event(Bar { target: bottom });
+    app.update();
+    assert_eq!(rx.recv(), Ok((bottom, (0, None, vec![top]))));
+    assert_eq!(rx.recv(), Ok((top, (1, Some(bottom), vec![]))));
+    let stats = app
+        .world_mut()
+        .resource_mut::<crate::event_dispatcher::EventDispatcher<Bar>>()
+        .take_stats()
+        .unwrap();
+    // `bottom`, the `OnAny` on `middle`, and `top`.
+    assert_eq!(stats.listeners_invoked, 3);
+}
+
+#[test]
+fn dynamic_events() {
+    use crate::{dynamic::*, prelude::*, testing::DispatchLog};
+    use bevy::prelude::*;
+
+    let log = DispatchLog::<DynamicEvent>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(DynamicEventPlugin);
+    let mut registry = app.world_mut().resource_mut::<DynamicEventRegistry>();
+    let damage = registry.register("damage", true);
+    let heal = registry.register("heal", false);
+    assert_eq!(registry.id("damage"), Some(damage));
+    assert_eq!(registry.register("damage", true), damage);
+
+    let root = app.world_mut().spawn(log.listener()).id();
+    let absorbed = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = absorbed.clone();
+    let leaf = app
+        .world_mut()
+        .spawn(
+            DynamicListeners::default()
+                .on(damage, move |mut event: ListenerMut<DynamicEvent>| {
+                    let amount = event.payload_mut().downcast_mut::<u32>().unwrap();
+                    recorded.lock().unwrap().push(*amount);
+                    *amount -= 3;
+                })
+                .build(),
+        )
+        .set_parent(root)
+        .id();
+
+    let registry = app.world().resource::<DynamicEventRegistry>();
+    let events = [
+        registry.event(damage, leaf, 10u32),
+        registry.event(heal, leaf, 5u32),
+    ];
+    app.world_mut().send_event_batch(events);
+    app.update();
+
+    // The leaf only listens to damage, and heal does not bubble to the root.
+    assert_eq!(*absorbed.lock().unwrap(), [10]);
+    let records = log.take();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].event.id(), damage);
+    assert_eq!(records[0].event.payload().downcast_ref::<u32>(), Some(&7));
+
+    // Listeners without a callback for an event's id are skipped, and struct payloads keep their
+    // type as the event is cloned.
+    #[derive(Reflect)]
+    struct Knockback {
+        distance: f32,
+    }
+    let knockback = app
+        .world_mut()
+        .resource_mut::<DynamicEventRegistry>()
+        .register("knockback", true);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let top = app
+        .world_mut()
+        .spawn(
+            DynamicListeners::default()
+                .on(knockback, move |event: Listener<DynamicEvent>| {
+                    let distance = event
+                        .payload()
+                        .downcast_ref::<Knockback>()
+                        .unwrap()
+                        .distance;
+                    let path = (event.path_index(), event.previous_listener());
+                    tx.send((distance, path)).unwrap();
+                })
+                .build(),
+        )
+        .id();
+    let bottom = app
+        .world_mut()
+        .spawn(DynamicListeners::default().on(heal, || {}).build())
+        .set_parent(top)
+        .id();
+    app.world_mut()
+        .resource_mut::<crate::event_dispatcher::EventDispatcher<DynamicEvent>>()
+        .enable_stats();
+    let event = app.world().resource::<DynamicEventRegistry>().event(
+        knockback,
+        bottom,
+        Knockback { distance: 2.0 },
+    );
+    let mut paths = bevy::ecs::system::SystemState::<
+        crate::introspection::ListenerPaths<DynamicEvent>,
+    >::new(app.world_mut());
+    let path = paths.get(app.world()).path(&event);
+    assert_eq!(
+        path.iter().map(|node| node.listener).collect::<Vec<_>>(),
+        [top]
+    );
+    app.world_mut().send_event(event);
+    app.update();
+    assert_eq!(rx.try_recv(), Ok((2.0, (0, None))));
+    let stats = app
+        .world_mut()
+        .resource_mut::<crate::event_dispatcher::EventDispatcher<DynamicEvent>>()
+        .take_stats()
+        .unwrap();
+    assert_eq!(stats.listeners_invoked, 1);
+}
+
+#[test]
+fn event_inheritance() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct PointerDown(#[target] Entity);
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct PointerEvent(#[target] Entity);
+
+    impl From<PointerDown> for PointerEvent {
+        fn from(event: PointerDown) -> Self {
+            Self(event.0)
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let down = |calls: &Arc<Mutex<Vec<_>>>| {
+        let calls = calls.clone();
+        On::<PointerDown>::run(move |event: Listener<PointerDown>| {
+            calls.lock().unwrap().push(("down", event.listener()));
+        })
+    };
+    let general = |calls: &Arc<Mutex<Vec<_>>>, stop: bool| {
+        let calls = calls.clone();
+        On::<PointerEvent>::run(move |mut event: ListenerMut<PointerEvent>| {
+            calls.lock().unwrap().push(("general", event.listener()));
+            if stop {
+                event.stop_propagation();
+            }
+        })
+    };
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<PointerEvent>::default())
+        .add_plugins(EventListenerPlugin::<PointerDown>::default().dispatch_as::<PointerEvent>());
+    let root = app
+        .world_mut()
+        .spawn((down(&calls), general(&calls, false)))
+        .id();
+    let middle = app
+        .world_mut()
+        .spawn(general(&calls, false))
+        .set_parent(root)
+        .id();
+    let leaf = app.world_mut().spawn(down(&calls)).set_parent(middle).id();
+
+    // Listeners for the general event run after the specific listener on the same entity.
+    app.world_mut().send_event(PointerDown(leaf));
+    app.update();
+    assert_eq!(
+        std::mem::take(&mut *calls.lock().unwrap()),
+        [
+            ("down", leaf),
+            ("general", middle),
+            ("down", root),
+            ("general", root)
+        ]
+    );
+
+    // General events are still dispatched on their own.
+    app.world_mut().send_event(PointerEvent(leaf));
+    app.update();
+    assert_eq!(
+        std::mem::take(&mut *calls.lock().unwrap()),
+        [("general", middle), ("general", root)]
+    );
+
+    // Stopping the propagation of the general event stops the specific event.
+    app.world_mut()
+        .entity_mut(middle)
+        .insert(general(&calls, true));
+    app.world_mut().send_event(PointerDown(leaf));
+    app.update();
+    assert_eq!(
+        std::mem::take(&mut *calls.lock().unwrap()),
+        [("down", leaf), ("general", middle)]
+    );
+
+    // The path includes the listeners for both event types.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let sender = tx.clone();
+    let root = app
+        .world_mut()
+        .spawn(On::<PointerEvent>::run(
+            move |event: Listener<PointerEvent>| {
+                let remaining: Vec<_> = event.remaining_path().collect();
+                let path = (event.path_index(), event.previous_listener(), remaining);
+                sender.send((event.listener(), path)).unwrap();
+            },
+        ))
+        .id();
+    let middle = app.world_mut().spawn_empty().set_parent(root).id();
+    let leaf = app
+        .world_mut()
+        .spawn(On::<PointerDown>::run(
+            move |event: Listener<PointerDown>| {
+                let remaining: Vec<_> = event.remaining_path().collect();
+                let path = (event.path_index(), event.previous_listener(), remaining);
+                tx.send((event.listener(), path)).unwrap();
+            },
+        ))
+        .set_parent(middle)
+        .id();
+    app.world_mut().send_event(PointerDown(leaf));
+    app.update();
+    let received: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            (leaf, (0, None, vec![root])),
+            (root, (1, Some(leaf), vec![])),
+        ]
+    );
+}
+
+#[test]
+fn forward_events() {
+    use crate::{prelude::*, testing::DispatchLog};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Clicked {
+        #[target]
+        target: Entity,
+        owner: Entity,
+    }
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Activated(#[target] Entity);
+
+    let log = DispatchLog::<Activated>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Clicked>::default())
+        .add_plugins(EventListenerPlugin::<Activated>::default());
+    let window = app.world_mut().spawn(log.listener()).id();
+    let button = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(window)
+        .id();
+    let label = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(button)
+        .id();
+    let owner = app.world_mut().spawn(log.listener()).id();
+
+    let send = |app: &mut App, from: ForwardFrom<Clicked>| {
+        app.world_mut()
+            .entity_mut(button)
+            .insert(On::<Clicked>::forward(from, |_, entity| Activated(entity)));
+        app.world_mut().send_event(Clicked {
+            target: label,
+            owner,
+        });
+        app.update();
+        // The forwarded event is dispatched on the next frame.
+        assert!(log.take().is_empty());
+        app.update();
+    };
+
+    send(&mut app, ForwardFrom::Listener);
+    log.assert_dispatched_in_order(&[button, window]);
+    log.clear();
+
+    send(&mut app, ForwardFrom::Target);
+    log.assert_dispatched_in_order(&[label, button, window]);
+    log.clear();
+
+    send(
+        &mut app,
+        ForwardFrom::computed(|event: &ListenerInput<Clicked>| event.owner),
+    );
+    log.assert_dispatched_in_order(&[owner]);
+}
+
+#[test]
+fn listener_templates() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Hit(#[target] Entity);
+
+    let hits = Arc::new(Mutex::new(Vec::new()));
+    let recorded = hits.clone();
+    let template = ListenerTemplate::new(move || {
+        let recorded = recorded.clone();
+        On::<Hit>::run(move |event: Listener<Hit>, mut count: Local<u32>| {
+            *count += 1;
+            recorded.lock().unwrap().push((event.listener(), *count));
+        })
+    });
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Hit>::default());
+    let on_hit = template.build();
+    let copy = on_hit.try_clone().unwrap();
+    let a = app.world_mut().spawn(on_hit).id();
+    let b = app.world_mut().spawn(copy).id();
+    let c = app.world_mut().spawn(template.build()).id();
+
+    for target in [a, a, b, c] {
+        app.world_mut().send_event(Hit(target));
+    }
+    app.update();
+    // Each listener has its own callback state.
+    assert_eq!(*hits.lock().unwrap(), [(a, 1), (a, 2), (b, 1), (c, 1)]);
+}
+
+#[test]
+fn try_clone_listener() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Hit(#[target] Entity);
+
+    let template = ListenerTemplate::new(|| On::<Hit>::run(|| {}));
+    let copy = template.build().try_clone();
+    // Copies are built from the same template, so they can be copied again.
+    assert!(copy.and_then(|copy| copy.try_clone()).is_some());
+    // Listeners that weren't built from a template can't be cloned.
+    assert!(On::<Hit>::run(|| {}).try_clone().is_none());
+}
+
+#[test]
+fn shared_callback_system() {
+    use crate::prelude::*;
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Clicked(#[target] Entity);
+
+    #[derive(Resource, Default)]
+    struct Clicks(Vec<Entity>);
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Clicked>::default())
+        .init_resource::<Clicks>();
+    let handle_click = SharedCallback::new(
+        |mut event: ListenerMut<Clicked>, mut clicks: ResMut<Clicks>| {
+            clicks.0.push(event.listener());
+            if clicks.0.len() == 4 {
+                event.stop_propagation();
+            }
+        },
+    );
+    let root = app
+        .world_mut()
+        .spawn(On::<Clicked>::run_shared(&handle_click))
+        .id();
+    let child = app
+        .world_mut()
+        .spawn(On::<Clicked>::run_shared(&handle_click))
+        .set_parent(root)
+        .id();
+    let other = app
+        .world_mut()
+        .spawn(On::<Clicked>::run_shared(&handle_click))
+        .id();
+
+    app.world_mut().send_event(Clicked(child));
+    app.world_mut().send_event(Clicked(other));
+    app.world_mut().send_event(Clicked(child));
+    app.update();
+    assert_eq!(
+        app.world().resource::<Clicks>().0,
+        [child, root, other, child]
+    );
+
+    // Clones of a listener run the same shared callback.
+    let listener = app.world().get::<On<Clicked>>(other).unwrap();
+    let listener = listener.try_clone().unwrap();
+    let clone = app.world_mut().spawn(listener).id();
+    app.world_mut().send_event(Clicked(clone));
+    app.update();
+    assert_eq!(app.world().resource::<Clicks>().0[4..], [clone]);
+}
+
+#[test]
+fn dead_target_events() {
+    use crate::{event_dispatcher::DeadTargetEvent, prelude::*, testing::DispatchLog};
+    use bevy::prelude::*;
+
+    #[derive(Clone, Event)]
+    struct Attack(Vec<Entity>);
+
+    impl EntityEvent for Attack {
+        fn target(&self) -> Entity {
+            self.0[0]
+        }
+        fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+            self.0.iter().copied()
+        }
+    }
+
+    let log = DispatchLog::<Attack>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Attack>::default());
+    let alive = app.world_mut().spawn(log.listener()).id();
+    let despawned = app.world_mut().spawn(log.listener()).id();
+    app.world_mut().despawn(despawned);
+
+    app.world_mut().send_event(Attack(vec![despawned, alive]));
+    app.update();
+    // The event is still dispatched to targets that exist.
+    log.assert_dispatched_in_order(&[alive]);
+    let dead: Vec<_> = app
+        .world_mut()
+        .resource_mut::<Events<DeadTargetEvent<Attack>>>()
+        .drain()
+        .map(|dead| (dead.target, dead.event.0))
+        .collect();
+    assert_eq!(dead, [(despawned, vec![despawned, alive])]);
+}
+
+#[test]
+fn retarget_to_surviving_ancestor() {
+    use crate::{
+        event_dispatcher::{DeadTargetEvent, RetargetingEventWriter},
+        prelude::*,
+        testing::DispatchLog,
+    };
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+    #[derive(Clone, Event, EntityEvent)]
+    #[can_bubble]
+    struct Destroyed(#[target] Entity);
+
+    let log = DispatchLog::<Destroyed>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Destroyed>::default());
+    let root = app.world_mut().spawn(log.listener()).id();
+    let middle = app.world_mut().spawn_empty().set_parent(root).id();
+    let leaf = app
+        .world_mut()
+        .spawn(log.listener())
+        .set_parent(middle)
+        .id();
+
+    // The retargeted event starts from the nearest ancestor that still exists, even if it has no
+    // listener. The event sent without recording the ancestry is a dead target event.
+    app.world_mut()
+        .run_system_once(move |mut events: RetargetingEventWriter<Destroyed>| {
+            events.send(Destroyed(leaf));
+        });
+    app.world_mut().send_event(Destroyed(leaf));
+    app.world_mut().despawn(leaf);
+    app.update();
+    let records = log.take();
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].listener, records[0].target), (root, middle));
+    assert_eq!(records[0].event.0, leaf);
+    let dead = app
+        .world_mut()
+        .resource_mut::<Events<DeadTargetEvent<Destroyed>>>()
+        .drain()
+        .count();
+    assert_eq!(dead, 1);
+
+    // Events are dead targets once every recorded ancestor is gone.
+    let leaf = app.world_mut().spawn(log.listener()).set_parent(root).id();
+    app.world_mut()
+        .run_system_once(move |mut events: RetargetingEventWriter<Destroyed>| {
+            events.send(Destroyed(leaf));
+        });
+    app.world_mut().entity_mut(root).despawn_recursive();
+    app.update();
+    assert!(log.take().is_empty());
+    let dead = app
+        .world_mut()
+        .resource_mut::<Events<DeadTargetEvent<Destroyed>>>()
+        .drain()
+        .count();
+    assert_eq!(dead, 1);
+}
+
+#[test]
+fn delayed_events() {
+    use crate::{delayed::DelayedEvents, prelude::*, testing::DispatchLog};
+    use bevy::{ecs::system::RunSystemOnce, prelude::*, time::TimeUpdateStrategy};
+    use std::time::Duration;
+
+    #[derive(Clone, Event, EntityEvent)]
+    struct Damage(#[target] Entity);
+
+    let log = DispatchLog::<Damage>::default();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(EventListenerPlugin::<Damage>::default())
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+    let target = app.world_mut().spawn(log.listener()).id();
+    app.update();
+
+    let send = |app: &mut App, delay| {
+        app.world_mut()
+            .run_system_once(move |mut commands: Commands| {
+                commands.send_delayed(Damage(target), delay)
+            })
+    };
+    // Counts the frames until the event is dispatched.
+    let frames_until_dispatched = |app: &mut App| {
+        (1..10).find(|_| {
+            app.update();
+            !log.take().is_empty()
+        })
+    };
+
+    send(&mut app, Delay::Frames(3));
+    assert_eq!(frames_until_dispatched(&mut app), Some(3));
+    send(&mut app, Delay::Frames(0));
+    assert_eq!(frames_until_dispatched(&mut app), Some(1));
+    send(&mut app, Delay::Time(Duration::from_millis(250)));
+    assert_eq!(frames_until_dispatched(&mut app), Some(3));
+
+    // Cancelled events and events whose target was despawned are dropped.
+    send(&mut app, Delay::Frames(2)).cancel();
+    assert_eq!(frames_until_dispatched(&mut app), None);
+    send(&mut app, Delay::Frames(2));
+    app.world_mut().despawn(target);
+    app.update();
+    assert!(app.world().resource::<DelayedEvents<Damage>>().is_empty());
+
+    // Events with several targets are only dropped once every target is despawned.
+    #[derive(Clone, Event)]
+    struct Explosion(Vec<Entity>);
+
+    impl EntityEvent for Explosion {
+        fn target(&self) -> Entity {
+            self.0[0]
+        }
+        fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+            self.0.iter().copied()
+        }
+    }
+
+    let log = DispatchLog::<Explosion>::default();
+    app.add_plugins(EventListenerPlugin::<Explosion>::default());
+    let first = app.world_mut().spawn(log.listener()).id();
+    let second = app.world_mut().spawn(log.listener()).id();
+    app.world_mut()
+        .run_system_once(move |mut commands: Commands| {
+            commands.send_delayed(Explosion(vec![first, second]), Delay::Frames(2))
+        });
+    app.world_mut().despawn(first);
+    app.update();
+    app.update();
+    log.assert_dispatched_in_order(&[second]);
+}
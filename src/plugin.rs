@@ -3,35 +3,153 @@
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 
-use crate::{event_dispatcher::EventDispatcher, event_listener::EntityEvent};
+use crate::{
+    delayed::DelayedEvents,
+    event_dispatcher::{
+        invalidate_listener_graphs, DeadTargetEvent, EventDispatcher, GeneralListener,
+        ListenerGraphInvalidation, SentAncestry,
+    },
+    event_listener::EntityEvent,
+    introspection::ListenerGraphSnapshot,
+};
 
 /// The [`SystemSet`] that event listener plugins are added to.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub struct EventListenerSet;
 
+/// The stages of event dispatch, which run in order inside the [`EventListenerSet`].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, SystemSet)]
+pub enum EventDispatchSet {
+    /// Builds the listener graph for the events sent this frame. This only needs read access to the
+    /// hierarchy and listeners, so graphs for different event types are built in parallel.
+    BuildGraph,
+    /// Bubbles events through the listener graph, running callbacks. Callbacks need exclusive
+    /// access to the [`World`], so event types are bubbled one at a time, after every graph has been
+    /// built. Events sent by callbacks are dispatched on the next frame.
+    Bubble,
+}
+
 /// Adds event listening and bubbling support for event `E`.
-pub struct EventListenerPlugin<E: EntityEvent>(std::marker::PhantomData<E>);
+pub struct EventListenerPlugin<E: EntityEvent> {
+    phantom: std::marker::PhantomData<E>,
+    generalizations: Vec<GeneralListener>,
+}
 
 impl<E: EntityEvent> Default for EventListenerPlugin<E> {
     fn default() -> Self {
-        Self(std::marker::PhantomData)
+        Self {
+            phantom: std::marker::PhantomData,
+            generalizations: Vec::new(),
+        }
+    }
+}
+
+impl<E: EntityEvent> EventListenerPlugin<E> {
+    /// Also dispatch events of type `E` as the more general event type `G`, so [`On<G>`] listeners
+    /// run for events of type `E`, converted with [`From`]. This lets one listener handle a family
+    /// of events, e.g. an `On<PointerEvent>` for `PointerDown` and `PointerUp`. The event type `G`
+    /// needs its own [`EventListenerPlugin`].
+    ///
+    /// As the event propagates, the listeners on each entity are run in order: the [`On<E>`], then
+    /// the [`On<G>`] for each general event type, in the order they were added. Stopping the
+    /// propagation of the converted event stops the original event too, but other changes to the
+    /// converted event are not seen by later listeners. Batch listeners for `G` only receive events
+    /// of type `G`.
+    ///
+    /// Events of type `E` walk the hierarchy while they are dispatched, instead of using the cached
+    /// listener graph, which is slower for deep hierarchies. Listeners are not deduplicated for
+    /// events with several targets.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_eventlistener::prelude::*;
+    /// #[derive(Clone, Event, EntityEvent)]
+    /// #[can_bubble]
+    /// struct PointerDown(#[target] Entity);
+    ///
+    /// #[derive(Clone, Event, EntityEvent)]
+    /// #[can_bubble]
+    /// struct PointerEvent(#[target] Entity);
+    ///
+    /// impl From<PointerDown> for PointerEvent {
+    ///     fn from(event: PointerDown) -> Self {
+    ///         Self(event.0)
+    ///     }
+    /// }
+    ///
+    /// App::new()
+    ///     .add_plugins(EventListenerPlugin::<PointerEvent>::default())
+    ///     .add_plugins(EventListenerPlugin::<PointerDown>::default().dispatch_as::<PointerEvent>());
+    /// ```
+    ///
+    /// [`On<E>`]: crate::event_listener::On
+    /// [`On<G>`]: crate::event_listener::On
+    pub fn dispatch_as<G: EntityEvent + From<E>>(mut self) -> Self {
+        self.generalizations.push(GeneralListener::new::<E, G>());
+        self
     }
 }
 
 impl<E: EntityEvent> Plugin for EventListenerPlugin<E> {
     fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<ListenerGraphInvalidation>() {
+            app.init_resource::<ListenerGraphInvalidation>()
+                .configure_sets(
+                    PreUpdate,
+                    (EventDispatchSet::BuildGraph, EventDispatchSet::Bubble)
+                        .chain()
+                        .in_set(EventListenerSet),
+                )
+                .add_systems(
+                    PreUpdate,
+                    invalidate_listener_graphs
+                        .run_if(ListenerGraphInvalidation::is_requested)
+                        .in_set(EventDispatchSet::BuildGraph),
+                );
+        }
+        app.world_mut()
+            .resource_mut::<ListenerGraphInvalidation>()
+            .register::<E>();
+
         app.add_event::<E>()
-            .insert_resource(EventDispatcher::<E>::default())
+            .add_event::<DeadTargetEvent<E>>()
+            .init_resource::<SentAncestry<E>>()
+            .init_resource::<DelayedEvents<E>>()
+            .insert_resource(EventDispatcher::<E> {
+                generalizations: self.generalizations.clone(),
+                ..Default::default()
+            })
             .add_systems(
                 PreUpdate,
                 (
-                    EventDispatcher::<E>::build,
-                    EventDispatcher::<E>::bubble_events,
-                    EventDispatcher::<E>::cleanup,
+                    (
+                        EventDispatcher::<E>::track_removed,
+                        ListenerGraphInvalidation::request.run_if(on_event::<E>()),
+                    )
+                        .before(invalidate_listener_graphs),
+                    (
+                        EventDispatcher::<E>::cleanup,
+                        EventDispatcher::<E>::build,
+                        ListenerGraphSnapshot::<E>::capture
+                            .run_if(resource_exists::<ListenerGraphSnapshot<E>>),
+                    )
+                        .chain()
+                        .after(invalidate_listener_graphs)
+                        .run_if(on_event::<E>()),
                 )
-                    .chain()
-                    .run_if(on_event::<E>())
-                    .in_set(EventListenerSet),
+                    .in_set(EventDispatchSet::BuildGraph),
+            )
+            .add_systems(
+                PreUpdate,
+                DelayedEvents::<E>::release
+                    .in_set(EventListenerSet)
+                    .before(EventDispatchSet::BuildGraph),
+            )
+            .add_systems(
+                PreUpdate,
+                EventDispatcher::<E>::bubble_events
+                    .run_if(EventDispatcher::<E>::has_events)
+                    .in_set(EventDispatchSet::Bubble),
             );
     }
 }
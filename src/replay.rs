@@ -0,0 +1,276 @@
+//! Record [`EntityEvent`]s during a session, save them to a file, and replay them later, e.g. in a
+//! headless [`App`] to reproduce a bug.
+//!
+//! Add an [`EventReplayPlugin<E>`] for each event type you want to record or replay. Recording
+//! starts when an [`EventRecorder<E>`] resource is inserted, and replaying starts when an
+//! [`EventReplayer<E>`] resource is inserted.
+//!
+//! Entities are not stable between sessions, so every entity referenced by a recorded event is
+//! stored alongside its [`ReplayId`], if it has one. When replaying, these entities are remapped to
+//! the entities in the new world with the same [`ReplayId`], or to entities mapped manually with
+//! [`EventReplayer::map_entity`]. Events that reference an entity that cannot be remapped are
+//! dropped.
+//!
+//! Only events sent outside of event dispatch are recorded. Events sent by listener callbacks, and
+//! delayed events released by the [`EventListenerSet`], are sent again when the events that caused
+//! them are replayed, so recording them would replay them twice.
+
+use std::{io, marker::PhantomData, path::Path};
+
+use bevy_app::prelude::*;
+use bevy_ecs::{
+    entity::{EntityHashMap, EntityMapper, MapEntities},
+    event::ManualEventReader,
+    prelude::*,
+};
+use bevy_utils::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{EntityEvent, EventListenerSet};
+
+/// An [`EntityEvent`] that can be recorded and replayed. This is implemented for all events that
+/// can be serialized and have their entities remapped.
+pub trait ReplayableEvent: EntityEvent + MapEntities + Serialize + DeserializeOwned {}
+
+impl<E: EntityEvent + MapEntities + Serialize + DeserializeOwned> ReplayableEvent for E {}
+
+/// A stable identifier for an entity, used to find the same entity when replaying events in another
+/// session. You are responsible for ensuring these are unique and deterministic, e.g. by assigning
+/// them when spawning a level.
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct ReplayId(pub u64);
+
+/// Adds systems to record and replay events of type `E`.
+pub struct EventReplayPlugin<E: ReplayableEvent>(PhantomData<E>);
+
+impl<E: ReplayableEvent> Default for EventReplayPlugin<E> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ReplayableEvent> Plugin for EventReplayPlugin<E> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (
+                (
+                    EventReplayer::<E>::send_events.run_if(resource_exists::<EventReplayer<E>>),
+                    EventRecorder::<E>::record_events.run_if(resource_exists::<EventRecorder<E>>),
+                )
+                    .chain()
+                    .before(EventListenerSet),
+                EventRecorder::<E>::skip_dispatched_events
+                    .run_if(resource_exists::<EventRecorder<E>>)
+                    .after(EventListenerSet),
+            ),
+        );
+    }
+}
+
+/// Events of type `E` that were recorded by an [`EventRecorder<E>`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "E: ReplayableEvent")]
+pub struct EventRecording<E: ReplayableEvent> {
+    /// The recorded events, in the order they were sent.
+    pub events: Vec<RecordedEvent<E>>,
+    /// The [`ReplayId`]s of entities referenced by the recorded events.
+    pub entities: Vec<(Entity, ReplayId)>,
+}
+
+/// An event in an [`EventRecording`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "E: ReplayableEvent")]
+pub struct RecordedEvent<E: ReplayableEvent> {
+    /// The frame the event was sent on, counting from the first frame of the recording.
+    pub frame: u64,
+    /// The event, referencing entities from the recorded session.
+    pub event: E,
+}
+
+impl<E: ReplayableEvent> Default for EventRecording<E> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl<E: ReplayableEvent> EventRecording<E> {
+    /// Serialize the recording as RON.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    /// Deserialize a recording from RON.
+    pub fn from_ron(ron: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(ron)
+    }
+
+    /// Write the recording to a RON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let ron = self
+            .to_ron()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, ron)
+    }
+
+    /// Read a recording from a RON file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let ron = std::fs::read_to_string(path)?;
+        Self::from_ron(&ron).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Records every event of type `E` sent outside of event dispatch while this resource exists.
+/// Remove the resource, or call [`EventRecorder::recording`] at any time, to get the
+/// [`EventRecording`].
+#[derive(Resource)]
+pub struct EventRecorder<E: ReplayableEvent> {
+    recording: EventRecording<E>,
+    replay_ids: EntityHashMap<ReplayId>,
+    frame: u64,
+    /// Reads the events to record. Events sent while events are dispatched are skipped.
+    reader: ManualEventReader<E>,
+}
+
+impl<E: ReplayableEvent> Default for EventRecorder<E> {
+    fn default() -> Self {
+        Self {
+            recording: EventRecording::default(),
+            replay_ids: EntityHashMap::default(),
+            frame: 0,
+            reader: ManualEventReader::default(),
+        }
+    }
+}
+
+impl<E: ReplayableEvent> EventRecorder<E> {
+    /// The events recorded so far.
+    pub fn recording(&self) -> &EventRecording<E> {
+        &self.recording
+    }
+
+    /// Stop recording, returning the events recorded so far.
+    pub fn into_recording(self) -> EventRecording<E> {
+        self.recording
+    }
+
+    fn record_events(
+        mut recorder: ResMut<Self>,
+        events: Res<Events<E>>,
+        replay_ids: Query<&ReplayId>,
+    ) {
+        let recorder = recorder.as_mut();
+        for event in recorder.reader.read(&events) {
+            let mut event = event.clone();
+            // Find the `ReplayId` of every entity in the event, without changing the event.
+            event.map_entities(&mut MapWith(|entity| {
+                if let Ok(replay_id) = replay_ids.get(entity) {
+                    if recorder.replay_ids.insert(entity, *replay_id).is_none() {
+                        recorder.recording.entities.push((entity, *replay_id));
+                    }
+                }
+                entity
+            }));
+            recorder.recording.events.push(RecordedEvent {
+                frame: recorder.frame,
+                event,
+            });
+        }
+        recorder.frame += 1;
+    }
+
+    /// Skip the events sent while events were dispatched, which are sent again when the events
+    /// that caused them are replayed.
+    fn skip_dispatched_events(mut recorder: ResMut<Self>, events: Res<Events<E>>) {
+        recorder.reader.clear(&events);
+    }
+}
+
+/// Replays the events in an [`EventRecording`] while this resource exists, sending each one on the
+/// same frame it was recorded on, counting from the frame this resource was inserted.
+#[derive(Resource)]
+pub struct EventReplayer<E: ReplayableEvent> {
+    recording: EventRecording<E>,
+    entity_map: EntityHashMap<Entity>,
+    next_event: usize,
+    frame: u64,
+}
+
+impl<E: ReplayableEvent> EventReplayer<E> {
+    /// Replay the events in `recording`.
+    pub fn new(recording: EventRecording<E>) -> Self {
+        Self {
+            recording,
+            entity_map: EntityHashMap::default(),
+            next_event: 0,
+            frame: 0,
+        }
+    }
+
+    /// Map an entity from the recorded session to an entity in this world. This takes precedence
+    /// over matching entities by [`ReplayId`].
+    pub fn map_entity(&mut self, recorded: Entity, entity: Entity) -> &mut Self {
+        self.entity_map.insert(recorded, entity);
+        self
+    }
+
+    /// Have all the recorded events been sent?
+    pub fn is_finished(&self) -> bool {
+        self.next_event >= self.recording.events.len()
+    }
+
+    fn send_events(
+        mut replayer: ResMut<Self>,
+        mut events: EventWriter<E>,
+        replay_ids: Query<(Entity, &ReplayId)>,
+    ) {
+        let replayer = replayer.as_mut();
+        let pending = &replayer.recording.events[replayer.next_event..];
+        let due = pending
+            .iter()
+            .take_while(|recorded| recorded.frame <= replayer.frame)
+            .count();
+
+        if due > 0 {
+            let live_entities: HashMap<ReplayId, Entity> = replay_ids
+                .iter()
+                .map(|(entity, replay_id)| (*replay_id, entity))
+                .collect();
+            let recorded_ids: EntityHashMap<ReplayId> =
+                replayer.recording.entities.iter().copied().collect();
+
+            for recorded in &pending[..due] {
+                let mut event = recorded.event.clone();
+                let mut all_mapped = true;
+                event.map_entities(&mut MapWith(|entity| {
+                    let mapped = replayer.entity_map.get(&entity).copied().or_else(|| {
+                        recorded_ids
+                            .get(&entity)
+                            .and_then(|replay_id| live_entities.get(replay_id).copied())
+                    });
+                    all_mapped &= mapped.is_some();
+                    mapped.unwrap_or(Entity::PLACEHOLDER)
+                }));
+                if all_mapped {
+                    events.send(event);
+                }
+            }
+            replayer.next_event += due;
+        }
+        replayer.frame += 1;
+    }
+}
+
+/// Maps entities with a closure.
+struct MapWith<F>(F);
+
+impl<F: FnMut(Entity) -> Entity> EntityMapper for MapWith<F> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        (self.0)(entity)
+    }
+}
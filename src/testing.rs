@@ -0,0 +1,169 @@
+//! Utilities for testing how events propagate through event listeners.
+//!
+//! A [`DispatchLog`] provides event listeners that record every event that reaches them into a
+//! shared buffer, which can then be checked with helpers like
+//! [`DispatchLog::assert_dispatched_in_order`].
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_eventlistener::{prelude::*, testing::DispatchLog};
+//! # #[derive(Clone, Event, EntityEvent)]
+//! # #[can_bubble]
+//! # struct Attack {
+//! #     #[target]
+//! #     target: Entity,
+//! # }
+//! let log = DispatchLog::<Attack>::default();
+//! let mut app = App::new();
+//! app.add_plugins(EventListenerPlugin::<Attack>::default());
+//! let goblin = app.world_mut().spawn(log.listener()).id();
+//! let helmet = app.world_mut().spawn(log.listener()).set_parent(goblin).id();
+//!
+//! app.world_mut().send_event(Attack { target: helmet });
+//! app.update();
+//!
+//! log.assert_dispatched_in_order(&[helmet, goblin]);
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bevy_ecs::prelude::*;
+
+use crate::{
+    callbacks::{Listener, ListenerInput, ListenerMut},
+    event_listener::On,
+    EntityEvent,
+};
+
+/// Where a listener was, relative to the target, when an event reached it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// The listener is on the entity targeted by the event.
+    AtTarget,
+    /// The event bubbled up to the listener from one of its descendants.
+    Bubbling,
+    /// The event was [broadcast](EntityEvent::broadcast) down to the listener from one of its
+    /// ancestors.
+    Broadcasting,
+}
+
+/// A record of an event reaching a listener created by a [`DispatchLog`].
+#[derive(Clone, Debug)]
+pub struct DispatchRecord<E: EntityEvent> {
+    /// The entity that was listening for the event.
+    pub listener: Entity,
+    /// The entity that was targeted by the event.
+    pub target: Entity,
+    /// A copy of the event when it reached the listener, including changes made by any listeners
+    /// that ran before this one.
+    pub event: E,
+    /// Where the listener was relative to the target.
+    pub phase: Phase,
+}
+
+/// A shared buffer of [`DispatchRecord`]s, written to by the event listeners it creates. Clones of
+/// the log share the same buffer.
+pub struct DispatchLog<E: EntityEvent>(Arc<Mutex<Vec<DispatchRecord<E>>>>);
+
+impl<E: EntityEvent> Default for DispatchLog<E> {
+    fn default() -> Self {
+        Self(Arc::default())
+    }
+}
+
+impl<E: EntityEvent> Clone for DispatchLog<E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<E: EntityEvent> DispatchLog<E> {
+    /// An event listener that records every event that reaches it in this log.
+    pub fn listener(&self) -> On<E> {
+        let log = self.clone();
+        On::<E>::run(move |event: Listener<E>| log.push(&event))
+    }
+
+    /// An event listener that records every event that reaches it in this log, then stops the
+    /// event from propagating further.
+    pub fn stopping_listener(&self) -> On<E> {
+        let log = self.clone();
+        On::<E>::run(move |mut event: ListenerMut<E>| {
+            log.push(&event);
+            event.stop_propagation();
+        })
+    }
+
+    /// All records in the order they were written.
+    pub fn records(&self) -> Vec<DispatchRecord<E>> {
+        self.lock().clone()
+    }
+
+    /// The listeners that were reached, in the order they were reached.
+    pub fn listeners(&self) -> Vec<Entity> {
+        self.lock().iter().map(|record| record.listener).collect()
+    }
+
+    /// Remove all records from the log, returning them.
+    pub fn take(&self) -> Vec<DispatchRecord<E>> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Remove all records from the log.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Assert that exactly these listeners were reached, in this order.
+    #[track_caller]
+    pub fn assert_dispatched_in_order(&self, listeners: &[Entity]) {
+        assert_eq!(
+            self.listeners(),
+            listeners,
+            "listeners were not reached in the expected order"
+        );
+    }
+
+    /// Assert that exactly these `(listener, target)` pairs were recorded, in this order.
+    #[track_caller]
+    pub fn assert_dispatched_in_order_with_targets(&self, expected: &[(Entity, Entity)]) {
+        let actual: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|record| (record.listener, record.target))
+            .collect();
+        assert_eq!(
+            actual, expected,
+            "(listener, target) pairs were not recorded in the expected order"
+        );
+    }
+
+    /// Assert that no listeners were reached.
+    #[track_caller]
+    pub fn assert_not_dispatched(&self) {
+        self.assert_dispatched_in_order(&[]);
+    }
+
+    fn push(&self, event: &ListenerInput<E>) {
+        let (listener, target) = (event.listener(), event.target());
+        let phase = if listener == target {
+            Phase::AtTarget
+        } else if event.broadcast().is_some() {
+            Phase::Broadcasting
+        } else {
+            Phase::Bubbling
+        };
+        self.lock().push(DispatchRecord {
+            listener,
+            target,
+            event: (**event).clone(),
+            phase,
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<DispatchRecord<E>>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
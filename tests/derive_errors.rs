@@ -0,0 +1,7 @@
+//! Checks the errors reported by the `EntityEvent` derive. Run with `TRYBUILD=overwrite` to update
+//! the expected output after changing a diagnostic.
+
+#[test]
+fn derive_errors() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
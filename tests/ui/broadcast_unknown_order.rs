@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[broadcast(random)]
+struct Attack {
+    #[target]
+    target: Entity,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[can_bubble = "bubbles"]
+enum Pointer {
+    Down {
+        #[target]
+        target: Entity,
+        bubbles: bool,
+    },
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[can_bubble = "bubbles(1)"]
+struct Attack {
+    #[target]
+    target: Entity,
+}
+
+fn main() {}
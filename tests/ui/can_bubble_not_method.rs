@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[can_bubble = "Self::bubbles()"]
+struct Attack {
+    #[target]
+    target: Entity,
+}
+
+fn main() {}
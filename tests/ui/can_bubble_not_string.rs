@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[can_bubble = true]
+struct Attack {
+    #[target]
+    target: Entity,
+    bubbles: bool,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+struct Attack {
+    #[target]
+    attacker: Entity,
+    #[target]
+    target: Entity,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+enum Pointer {}
+
+fn main() {}
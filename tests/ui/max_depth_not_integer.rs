@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+#[max_depth = "3"]
+struct Attack {
+    #[target]
+    target: Entity,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+struct Attack {
+    target: Entity,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+struct Attack {
+    #[target(entity)]
+    target: Entity,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Copy, Event, EntityEvent)]
+union Attack {
+    target: Entity,
+}
+
+fn main() {}
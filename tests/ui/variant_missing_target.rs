@@ -0,0 +1,10 @@
+use bevy::prelude::*;
+use bevy_eventlistener::prelude::*;
+
+#[derive(Clone, Event, EntityEvent)]
+enum Pointer {
+    Down(#[target] Entity),
+    Up(Entity),
+}
+
+fn main() {}